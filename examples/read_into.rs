@@ -0,0 +1,80 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Compares the cost of counting reads with `read_into()` against the other paths.
+//!
+//! The same in-memory stream is read in small chunks directly, through `ReadCounter::read` and
+//! through `ReadCounter::read_into`, with a caller-owned total. Run with
+//! `cargo run --release --example read_into`.
+
+extern crate voncount;
+
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+
+use voncount::{Counter, ReadCounter};
+
+const SIZE: usize = 64 << 20;
+const CHUNK: usize = 64;
+const ROUNDS: u32 = 8;
+
+fn measure<F: FnMut(&[u8]) -> io::Result<usize>>(name: &str, data: &[u8], mut read: F) {
+    let mut elapsed = Duration::from_secs(0);
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        let total = read(data).unwrap();
+        elapsed += start.elapsed();
+        assert_eq!(total, data.len());
+    }
+
+    let reads = ROUNDS as f64 * (data.len() / CHUNK) as f64;
+    let nanos = elapsed.as_secs_f64() * 1e9 / reads;
+    println!("{:<10} {:.2} ns per read", name, nanos);
+}
+
+fn main() {
+    let data = vec![1u8; SIZE];
+
+    measure("direct", &data, |mut d| {
+        let r = &mut d;
+        let (mut buffer, mut total) = ([0u8; CHUNK], 0);
+        loop {
+            match r.read(&mut buffer)? {
+                0 => return Ok(total),
+                size => total += size,
+            }
+        }
+    });
+
+    measure("read", &data, |mut d| {
+        let mut r = ReadCounter::from(&mut d);
+        let mut buffer = [0u8; CHUNK];
+        while r.read(&mut buffer)? > 0 {}
+        Ok(r.count())
+    });
+
+    measure("read_into", &data, |mut d| {
+        let mut r = ReadCounter::from(&mut d);
+        let (mut buffer, mut total) = ([0u8; CHUNK], 0);
+        loop {
+            if r.read_into(&mut buffer, &mut total)? == 0 {
+                return Ok(total);
+            }
+        }
+    });
+}
@@ -0,0 +1,105 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Compares `read_to_end` through a `ReadCounter` with reading a file directly.
+//!
+//! A wrapper which only forwards `read` hides the size hint that `File` uses to pre-allocate, so
+//! the buffer is grown (and copied) repeatedly. `ReadCounter` forwards `read_to_end`, so it should
+//! match the direct path. Run with `cargo run --release --example read_to_end`.
+
+extern crate voncount;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use voncount::{Counter, ReadCounter};
+
+// An allocator which counts allocations and reallocations.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// A wrapper which only forwards `read`, like `ReadCounter` before it forwarded `read_to_end`.
+struct ReadOnly<'a>(&'a mut File);
+
+impl<'a> Read for ReadOnly<'a> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buffer)
+    }
+}
+
+fn measure<F>(name: &str, path: &Path, mut read: F) -> io::Result<()>
+where
+    F: FnMut(&mut File, &mut Vec<u8>) -> io::Result<usize>,
+{
+    let mut file = File::open(path)?;
+    let mut buffer = Vec::new();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let size = read(&mut file, &mut buffer)?;
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    println!(
+        "{:<12} {} bytes, {} allocations, {:?}",
+        name, size, allocations, elapsed
+    );
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let path = env::temp_dir().join(format!("voncount-read-to-end-{}", std::process::id()));
+    File::create(&path)?.write_all(&vec![0u8; 64 << 20])?;
+
+    measure("direct", &path, |file, buffer| file.read_to_end(buffer))?;
+    measure("read only", &path, |file, buffer| {
+        ReadOnly(file).read_to_end(buffer)
+    })?;
+    measure("ReadCounter", &path, |file, buffer| {
+        let mut r = ReadCounter::from(file);
+        r.read_to_end(buffer)?;
+        Ok(r.count())
+    })?;
+
+    fs::remove_file(&path)
+}
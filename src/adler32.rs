@@ -0,0 +1,140 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+const MOD: u32 = 65521;
+
+// The largest number of bytes that can be summed before `b` may overflow a `u32`.
+const NMAX: usize = 5552;
+
+/// Wraps any implementation of `std::io::Write`, counting the bytes written and computing their
+/// Adler-32 checksum.
+///
+/// An `Adler32WriteCounter` behaves just like a `WriteCounter`. However, it also maintains a
+/// rolling Adler-32 checksum (as used by zlib) of the bytes written. Only bytes accepted by the
+/// inner writer are included in the checksum, so short writes are accounted for correctly.
+pub struct Adler32WriteCounter<'a, T: 'a + io::Write> {
+    writer: &'a mut T,
+    count: usize,
+    a: u32,
+    b: u32,
+}
+
+impl<'a, T: 'a + io::Write> From<&'a mut T> for Adler32WriteCounter<'a, T> {
+    /// Creates an `Adler32WriteCounter` by wrapping any implementation of `std::io::Write`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> Adler32WriteCounter<'a, T> {
+        Adler32WriteCounter {
+            writer: value,
+            count: 0,
+            a: 1,
+            b: 0,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Write> Adler32WriteCounter<'a, T> {
+    /// Returns the Adler-32 checksum of the bytes written so far.
+    pub fn adler32(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+
+    fn update(&mut self, buffer: &[u8]) {
+        for chunk in buffer.chunks(NMAX) {
+            for byte in chunk {
+                self.a += u32::from(*byte);
+                self.b += self.a;
+            }
+
+            self.a %= MOD;
+            self.b %= MOD;
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Write> Counter for Adler32WriteCounter<'a, T> {
+    /// Returns the number of bytes written so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Write> io::Write for Adler32WriteCounter<'a, T> {
+    /// Proxies to the inner `write` function, counting and checksumming the bytes written.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        let size = self.writer.write(buffer)?;
+        self.update(&buffer[..size]);
+        self.count += size;
+        Ok(size)
+    }
+
+    /// Proxies to the inner `flush` function.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use testutil::Short;
+
+    #[test]
+    fn adler32() {
+        let mut s = Short(Vec::new(), 4);
+        let mut w = Adler32WriteCounter::from(&mut s);
+        assert_eq!(w.adler32(), 1);
+
+        assert_eq!(w.write(b"Wikipedia").unwrap(), 4);
+        w.write_all(b"pedia").unwrap();
+
+        assert_eq!(w.count(), 9);
+        assert_eq!(w.adler32(), 0x11E6_0398);
+    }
+
+    #[test]
+    fn large() {
+        let data = vec![0xffu8; 3 * NMAX + 7];
+        let mut v = Vec::new();
+        let mut w = Adler32WriteCounter::from(&mut v);
+        w.write_all(&data).unwrap();
+
+        let (mut a, mut b) = (1u64, 0u64);
+        for byte in &data {
+            a = (a + u64::from(*byte)) % u64::from(MOD);
+            b = (b + a) % u64::from(MOD);
+        }
+
+        assert_eq!(w.count(), data.len());
+        assert_eq!(w.adler32(), ((b << 16) | a) as u32);
+    }
+}
@@ -0,0 +1,125 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and checking that the
+/// total is a multiple of an alignment.
+///
+/// An `AlignReadCounter` behaves just like a `ReadCounter`. Once the stream has been drained,
+/// call `verify_aligned()` to check that the stream consisted of whole blocks, which catches
+/// truncated or malformed block-structured data.
+pub struct AlignReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    alignment: usize,
+}
+
+impl<'a, T: 'a + io::Read> AlignReadCounter<'a, T> {
+    /// Creates an `AlignReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    ///
+    /// # Panics
+    ///
+    /// If `alignment` is zero.
+    #[must_use]
+    pub fn new(reader: &'a mut T, alignment: usize) -> AlignReadCounter<'a, T> {
+        assert!(alignment > 0, "alignment must be nonzero");
+
+        AlignReadCounter {
+            reader,
+            count: 0,
+            alignment,
+        }
+    }
+
+    /// Returns the alignment the total must be a multiple of.
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    /// Checks that the number of bytes read so far is a multiple of the alignment.
+    ///
+    /// This is intended to be called after the stream has been read to the end.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error of kind `InvalidData` if the count is not aligned.
+    pub fn verify_aligned(&self) -> io::Result<()> {
+        if self.count % self.alignment != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream length is not a multiple of the alignment",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for AlignReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for AlignReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn aligned() {
+        let data = [0u8; 10];
+        let mut out = Vec::new();
+
+        let mut d = &data[..8];
+        let mut r = AlignReadCounter::new(&mut d, 4);
+        r.read_to_end(&mut out).unwrap();
+        r.verify_aligned().unwrap();
+
+        let mut d = &data[..];
+        let mut r = AlignReadCounter::new(&mut d, 4);
+        r.read_to_end(&mut out).unwrap();
+        let err = r.verify_aligned().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(r.count(), 10);
+    }
+}
@@ -0,0 +1,102 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, adding the bytes read to an `AtomicUsize` owned by
+/// the caller.
+///
+/// An `AtomicReadCounter` behaves just like a `ReadCounter`. However, every successful read also
+/// performs a `fetch_add` on the provided atomic, so other threads can observe progress lock-free
+/// without the counter itself being shared. Several counters may feed the same atomic; each one's
+/// `count()` still reports only its own bytes.
+pub struct AtomicReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    atomic: &'a AtomicUsize,
+}
+
+impl<'a, T: 'a + io::Read> AtomicReadCounter<'a, T> {
+    /// Creates an `AtomicReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance
+    /// or the atomic.
+    #[must_use]
+    pub fn new(reader: &'a mut T, atomic: &'a AtomicUsize) -> AtomicReadCounter<'a, T> {
+        AtomicReadCounter {
+            reader,
+            count: 0,
+            atomic,
+        }
+    }
+
+    /// Returns the atomic this counter adds to.
+    pub fn atomic(&self) -> &'a AtomicUsize {
+        self.atomic
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for AtomicReadCounter<'a, T> {
+    /// Returns the number of bytes read so far through this counter.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for AtomicReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+        self.atomic.fetch_add(size, Ordering::Relaxed);
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn shared() {
+        let atomic = AtomicUsize::new(10);
+        let data = [1u8, 2, 3];
+        let mut d = &data[..];
+        let mut r = AtomicReadCounter::new(&mut d, &atomic);
+        let mut buf = [0u8; 2];
+
+        assert_eq!(r.read(&mut buf).unwrap(), 2);
+        assert_eq!(atomic.load(Ordering::Relaxed), 12);
+        assert_eq!(r.read(&mut buf).unwrap(), 1);
+        assert_eq!(atomic.load(Ordering::Relaxed), 13);
+        assert_eq!(r.count(), 3);
+    }
+}
@@ -0,0 +1,209 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Write`, counting the bytes written and flushing
+/// periodically.
+///
+/// An `AutoFlushWriteCounter` behaves just like a `WriteCounter`. However, once the number of bytes
+/// written since the last flush reaches the threshold, it flushes the wrapped writer
+/// automatically. This bounds the amount of data held in downstream buffers. Explicit calls to
+/// `flush` also reset the number of bytes since the last flush.
+///
+/// Since the bytes have already been written when an automatic flush fails, that `write` still
+/// succeeds. The flush error is returned by the next call to `write` or `flush` instead.
+pub struct AutoFlushWriteCounter<'a, T: 'a + io::Write> {
+    writer: &'a mut T,
+    count: usize,
+    threshold: usize,
+    unflushed: usize,
+    flushes: usize,
+    error: Option<io::Error>,
+}
+
+impl<'a, T: 'a + io::Write> AutoFlushWriteCounter<'a, T> {
+    /// Creates an `AutoFlushWriteCounter` by wrapping any implementation of `std::io::Write`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    ///
+    /// # Panics
+    ///
+    /// If `threshold` is zero.
+    #[must_use]
+    pub fn new(writer: &'a mut T, threshold: usize) -> AutoFlushWriteCounter<'a, T> {
+        assert!(threshold > 0, "threshold must be nonzero");
+
+        AutoFlushWriteCounter {
+            writer,
+            count: 0,
+            threshold,
+            unflushed: 0,
+            flushes: 0,
+            error: None,
+        }
+    }
+
+    /// Returns the number of automatic flushes performed so far.
+    ///
+    /// Explicit calls to `flush` are not included.
+    pub fn flushes(&self) -> usize {
+        self.flushes
+    }
+
+    /// Returns the number of bytes written since the last successful flush.
+    pub fn unflushed(&self) -> usize {
+        self.unflushed
+    }
+}
+
+impl<'a, T: 'a + io::Write> Counter for AutoFlushWriteCounter<'a, T> {
+    /// Returns the number of bytes written so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Write> io::Write for AutoFlushWriteCounter<'a, T> {
+    /// Proxies to the inner `write` function, counting the bytes written and flushing as needed.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying `write` function errors, or with the error of
+    /// a previous automatic flush which failed. In both cases nothing is written by this call. If
+    /// the automatic flush fails, the bytes just written are still counted and returned as
+    /// written, and the flush is attempted again after the next write.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        let size = self.writer.write(buffer)?;
+        self.count += size;
+        self.unflushed += size;
+
+        if self.unflushed >= self.threshold {
+            match self.writer.flush() {
+                Ok(()) => {
+                    self.unflushed = 0;
+                    self.flushes += 1;
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+
+        Ok(size)
+    }
+
+    /// Proxies to the inner `flush` function.
+    ///
+    /// On success, the number of bytes written since the last flush is reset to zero. If a
+    /// previous automatic flush failed, its error is returned instead, without flushing.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        self.writer.flush()?;
+        self.unflushed = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    struct Flushes(Vec<u8>, usize);
+
+    impl Write for Flushes {
+        fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+            self.0.write(buffer)
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            self.1 += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn autoflush() {
+        let mut f = Flushes(Vec::new(), 0);
+        let mut w = AutoFlushWriteCounter::new(&mut f, 10);
+
+        for _ in 0..9 {
+            w.write_all(b"abcd").unwrap();
+        }
+        assert_eq!(w.flushes(), 3);
+        assert_eq!(w.unflushed(), 0);
+
+        w.write_all(b"abcd").unwrap();
+        w.flush().unwrap();
+        assert_eq!(w.flushes(), 3);
+        assert_eq!(w.unflushed(), 0);
+        assert_eq!(w.count(), 40);
+        assert_eq!(f.1, 4);
+    }
+
+    struct FailsOnce(Vec<u8>, bool);
+
+    impl Write for FailsOnce {
+        fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+            self.0.write(buffer)
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            if self.1 {
+                return Ok(());
+            }
+
+            self.1 = true;
+            Err(io::Error::new(io::ErrorKind::Other, "flush failed"))
+        }
+    }
+
+    #[test]
+    fn failed_flush() {
+        let mut f = FailsOnce(Vec::new(), false);
+
+        {
+            let mut w = AutoFlushWriteCounter::new(&mut f, 4);
+            w.write_all(b"abcd").unwrap();
+            assert_eq!(w.unflushed(), 4);
+
+            let err = w.write_all(b"efgh").unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::Other);
+
+            w.write_all(b"efgh").unwrap();
+            w.flush().unwrap();
+            assert_eq!(w.flushes(), 1);
+            assert_eq!(w.count(), 8);
+        }
+
+        assert_eq!(&f.0[..], b"abcdefgh");
+    }
+}
@@ -0,0 +1,283 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use {Counter, TransformCounter};
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn sextet(byte: u8) -> Option<u32> {
+    let value = match byte {
+        b'A'..=b'Z' => byte - b'A',
+        b'a'..=b'z' => byte - b'a' + 26,
+        b'0'..=b'9' => byte - b'0' + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => return None,
+    };
+
+    Some(u32::from(value))
+}
+
+/// Wraps any implementation of `std::io::Read` containing base64 and counts the bytes decoded.
+///
+/// A `Base64ReadCounter` reads base64 text (using the standard alphabet) from the wrapped reader
+/// and yields the decoded bytes. It counts both the encoded bytes consumed from the wrapped reader
+/// and the decoded bytes delivered to the caller. Quartets which are split across reads are
+/// buffered internally. ASCII whitespace (such as line breaks) is skipped. The final quartet may
+/// omit its padding.
+///
+/// This type is only available with the `base64` feature.
+pub struct Base64ReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    encoded: usize,
+    decoded: usize,
+    quad: u32,
+    quad_len: usize,
+    padding: usize,
+    done: bool,
+    out: [u8; 3],
+    out_pos: usize,
+    out_len: usize,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for Base64ReadCounter<'a, T> {
+    /// Creates a `Base64ReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> Base64ReadCounter<'a, T> {
+        Base64ReadCounter {
+            reader: value,
+            encoded: 0,
+            decoded: 0,
+            quad: 0,
+            quad_len: 0,
+            padding: 0,
+            done: false,
+            out: [0u8; 3],
+            out_pos: 0,
+            out_len: 0,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> Base64ReadCounter<'a, T> {
+    /// Returns the number of encoded bytes consumed from the wrapped reader so far.
+    ///
+    /// This includes any whitespace and padding.
+    pub fn encoded_count(&self) -> usize {
+        self.encoded
+    }
+
+    /// Returns the number of decoded bytes delivered to the caller so far.
+    pub fn decoded_count(&self) -> usize {
+        self.decoded
+    }
+
+    // Consumes one encoded byte, returning any bytes decoded by completing a quartet.
+    fn push(&mut self, byte: u8) -> Result<&[u8], io::Error> {
+        match byte {
+            b' ' | b'\t' | b'\r' | b'\n' => return Ok(&[]),
+            _ if self.done => return Err(invalid("data found after base64 padding")),
+            b'=' if self.quad_len < 2 => return Err(invalid("unexpected base64 padding")),
+            b'=' => self.padding += 1,
+            _ if self.padding > 0 => return Err(invalid("data found after base64 padding")),
+            _ => match sextet(byte) {
+                Some(value) => self.quad |= value << (18 - 6 * self.quad_len),
+                None => return Err(invalid("invalid base64 character")),
+            },
+        }
+
+        self.quad_len += 1;
+        if self.quad_len < 4 {
+            return Ok(&[]);
+        }
+
+        Ok(self.decode())
+    }
+
+    // Decodes the sextets accumulated so far into `out`, returning the decoded bytes.
+    fn decode(&mut self) -> &[u8] {
+        let size = self.quad_len - self.padding - 1;
+
+        self.out = [
+            (self.quad >> 16) as u8,
+            (self.quad >> 8) as u8,
+            self.quad as u8,
+        ];
+        self.out_pos = 0;
+        self.out_len = size;
+
+        self.done = self.padding > 0;
+        self.quad = 0;
+        self.quad_len = 0;
+        self.padding = 0;
+
+        &self.out[..size]
+    }
+
+    // Decodes any unpadded final quartet at the end of the stream.
+    fn finish(&mut self) -> Result<(), io::Error> {
+        match (self.quad_len, self.padding) {
+            (0, _) => {}
+            (2, 0) | (3, 0) => {
+                self.decode();
+            }
+            _ => return Err(invalid("incomplete base64 quartet")),
+        }
+
+        self.done = true;
+        Ok(())
+    }
+
+    // Copies previously decoded, undelivered bytes into `buffer`.
+    fn drain(&mut self, buffer: &mut [u8]) -> usize {
+        let size = cmp::min(buffer.len(), self.out_len - self.out_pos);
+        buffer[..size].copy_from_slice(&self.out[self.out_pos..self.out_pos + size]);
+        self.out_pos += size;
+        size
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for Base64ReadCounter<'a, T> {
+    /// Returns the number of decoded bytes delivered to the caller so far.
+    fn count(&self) -> usize {
+        self.decoded
+    }
+}
+
+impl<'a, T: 'a + io::Read> TransformCounter for Base64ReadCounter<'a, T> {
+    /// Returns the number of encoded bytes consumed from the wrapped reader so far.
+    fn input_count(&self) -> usize {
+        self.encoded
+    }
+
+    /// Returns the number of decoded bytes delivered to the caller so far.
+    fn output_count(&self) -> usize {
+        self.decoded
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for Base64ReadCounter<'a, T> {
+    /// Reads from the inner reader, decoding base64 and counting the bytes along the way.
+    ///
+    /// This function calls the inner `read` function as many times as needed to decode at least
+    /// one byte or reach the end of the stream.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors. It will also return an error
+    /// of kind `InvalidData` if the stream does not contain valid base64.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let mut pos = self.drain(buffer);
+        let mut scratch = [0u8; 1024];
+
+        // Bound the input so that at most one quartet of output is left undelivered.
+        let max = cmp::min(scratch.len(), 4 * (buffer.len() / 3 + 1));
+
+        while pos == 0 && !buffer.is_empty() {
+            let size = self.reader.read(&mut scratch[..max])?;
+            self.encoded += size;
+
+            if size == 0 {
+                self.finish()?;
+                pos = self.drain(buffer);
+                break;
+            }
+
+            for byte in &scratch[..size] {
+                if !self.push(*byte)?.is_empty() {
+                    let room = cmp::min(self.out_len, buffer.len() - pos);
+                    buffer[pos..pos + room].copy_from_slice(&self.out[..room]);
+                    self.out_pos = room;
+                    pos += room;
+                }
+            }
+        }
+
+        self.decoded += pos;
+        Ok(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use testutil::Chunked;
+
+    #[test]
+    fn split() {
+        let mut c = Chunked(b"SGVsbG8s\nIFdvcmxk\r\nIQ==", 3);
+        let mut r = Base64ReadCounter::from(&mut c);
+        let mut out = Vec::new();
+        let mut buf = [0u8; 2];
+
+        loop {
+            match r.read(&mut buf).unwrap() {
+                0 => break,
+                n => out.extend_from_slice(&buf[..n]),
+            }
+        }
+
+        assert_eq!(&out[..], b"Hello, World!");
+        assert_eq!(r.encoded_count(), 23);
+        assert_eq!(r.decoded_count(), 13);
+        assert_eq!(r.count(), 13);
+    }
+
+    #[test]
+    fn unpadded() {
+        let mut d: &[u8] = b"SGk";
+        let mut r = Base64ReadCounter::from(&mut d);
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(&out[..], b"Hi");
+        assert_eq!(r.encoded_count(), 3);
+        assert_eq!(r.decoded_count(), 2);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut d: &[u8] = b"SG=k";
+        let mut r = Base64ReadCounter::from(&mut d);
+        let mut out = Vec::new();
+        let err = r.read_to_end(&mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn transform_counter() {
+        let mut d = &b"aGVsbG8="[..];
+        let mut r = Base64ReadCounter::from(&mut d);
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).unwrap();
+
+        let t: &dyn TransformCounter = &r;
+        assert_eq!((t.input_count(), t.output_count()), (8, 5));
+    }
+}
@@ -0,0 +1,169 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bits read.
+///
+/// A `BitReadCounter` supports bitstream formats, which may consume partial bytes. Bits are read
+/// most significant first with `read_bits()`, while whole bytes can still be read through
+/// `std::io::Read`. Both are tracked by `bit_count()`. `count()` reports the number of bytes read
+/// from the wrapped reader, including a partially consumed byte.
+///
+/// A byte-level `read` always starts on a byte boundary. So any bits remaining from a partially
+/// consumed byte are skipped, and count as consumed.
+pub struct BitReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    bits: u64,
+    byte: u8,
+    remaining: u32,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for BitReadCounter<'a, T> {
+    /// Creates a `BitReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> BitReadCounter<'a, T> {
+        BitReadCounter {
+            reader: value,
+            count: 0,
+            bits: 0,
+            byte: 0,
+            remaining: 0,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> BitReadCounter<'a, T> {
+    /// Returns the number of bits consumed so far.
+    pub fn bit_count(&self) -> u64 {
+        self.bits
+    }
+
+    /// Reads the next `n` bits, most significant first, returning them in the low bits of the
+    /// result.
+    ///
+    /// # Panics
+    ///
+    /// If `n` is greater than 64.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors (other than with the kind
+    /// `Interrupted`, which is retried). It will also return an error of kind `UnexpectedEof` if
+    /// the stream ends before `n` bits are read. Bits read before the error are still counted.
+    pub fn read_bits(&mut self, n: u32) -> io::Result<u64> {
+        assert!(n <= 64, "at most 64 bits can be read at once");
+
+        let mut value = 0u64;
+        let mut left = n;
+
+        while left > 0 {
+            if self.remaining == 0 {
+                let mut byte = [0u8; 1];
+                match self.reader.read(&mut byte) {
+                    Ok(0) => {
+                        let msg = "stream ended within a bit field";
+                        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, msg));
+                    }
+                    Ok(_) => {
+                        self.count += 1;
+                        self.byte = byte[0];
+                        self.remaining = 8;
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let take = left.min(self.remaining);
+            let shift = self.remaining - take;
+            let bits = (u32::from(self.byte) >> shift) & ((1u32 << take) - 1);
+
+            value = (value << take) | u64::from(bits);
+            self.remaining -= take;
+            self.bits += u64::from(take);
+            left -= take;
+        }
+
+        Ok(value)
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for BitReadCounter<'a, T> {
+    /// Returns the number of bytes read from the wrapped reader so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for BitReadCounter<'a, T> {
+    /// Skips to the next byte boundary, then proxies to the inner `read` function, counting the
+    /// bits read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        self.bits += u64::from(self.remaining);
+        self.remaining = 0;
+
+        let size = self.reader.read(buffer)?;
+        self.count += size;
+        self.bits += size as u64 * 8;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn bits() {
+        let data = [0b1011_0010u8, 0b0111_1111, 0xAB, 0xCD];
+        let mut d = &data[..];
+        let mut r = BitReadCounter::from(&mut d);
+
+        assert_eq!(r.read_bits(3).unwrap(), 0b101);
+        assert_eq!(r.read_bits(0).unwrap(), 0);
+        assert_eq!(r.read_bits(7).unwrap(), 0b100_1001);
+        assert_eq!(r.bit_count(), 10);
+        assert_eq!(r.count(), 2);
+
+        let mut buf = [0u8; 1];
+        r.read_exact(&mut buf).unwrap();
+        assert_eq!(buf[0], 0xAB);
+        assert_eq!(r.bit_count(), 24);
+
+        assert_eq!(r.read_bits(4).unwrap(), 0xC);
+        let err = r.read_bits(8).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(r.bit_count(), 32);
+        assert_eq!(r.count(), 4);
+    }
+}
@@ -0,0 +1,155 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Write`, counting the bytes written and padding the output
+/// to a block boundary on request.
+///
+/// A `BlockAlignWriteCounter` behaves just like a `WriteCounter`. However, `pad_to_block()` writes
+/// zero bytes until the total output is a multiple of the block size (such as the 512-byte records
+/// of a tar archive). Data and padding bytes are counted separately.
+pub struct BlockAlignWriteCounter<'a, T: 'a + io::Write> {
+    writer: &'a mut T,
+    data: usize,
+    padding: usize,
+    block_size: usize,
+}
+
+impl<'a, T: 'a + io::Write> BlockAlignWriteCounter<'a, T> {
+    /// Creates a `BlockAlignWriteCounter` by wrapping any implementation of `std::io::Write`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    ///
+    /// # Panics
+    ///
+    /// If `block_size` is zero.
+    #[must_use]
+    pub fn new(writer: &'a mut T, block_size: usize) -> BlockAlignWriteCounter<'a, T> {
+        assert!(block_size > 0, "block_size must be nonzero");
+
+        BlockAlignWriteCounter {
+            writer,
+            data: 0,
+            padding: 0,
+            block_size,
+        }
+    }
+
+    /// Returns the number of data bytes written so far.
+    pub fn data_count(&self) -> usize {
+        self.data
+    }
+
+    /// Returns the number of padding bytes written so far.
+    pub fn padding_count(&self) -> usize {
+        self.padding
+    }
+
+    /// Writes zero bytes until the total output is a multiple of the block size.
+    ///
+    /// If the output is already aligned, nothing is written.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying `write` function errors. Any padding written
+    /// before the error is still counted.
+    pub fn pad_to_block(&mut self) -> Result<(), io::Error> {
+        let zeros = [0u8; 512];
+        let rem = self.count() % self.block_size;
+        let mut needed = if rem == 0 { 0 } else { self.block_size - rem };
+
+        while needed > 0 {
+            let max = cmp::min(needed, zeros.len());
+
+            match self.writer.write(&zeros[..max]) {
+                Ok(0) => {
+                    let msg = "failed to write whole buffer";
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, msg));
+                }
+                Ok(size) => {
+                    self.padding += size;
+                    needed -= size;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, T: 'a + io::Write> Counter for BlockAlignWriteCounter<'a, T> {
+    /// Returns the total number of bytes written so far, including padding.
+    fn count(&self) -> usize {
+        self.data + self.padding
+    }
+}
+
+impl<'a, T: 'a + io::Write> io::Write for BlockAlignWriteCounter<'a, T> {
+    /// Proxies to the inner `write` function, counting the bytes written along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        let size = self.writer.write(buffer)?;
+        self.data += size;
+        Ok(size)
+    }
+
+    /// Proxies to the inner `flush` function.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn pad() {
+        let mut v: Vec<u8> = Vec::new();
+
+        {
+            let mut w = BlockAlignWriteCounter::new(&mut v, 512);
+            w.write_all(&[1u8; 700]).unwrap();
+            w.pad_to_block().unwrap();
+            assert_eq!(w.data_count(), 700);
+            assert_eq!(w.padding_count(), 324);
+            assert_eq!(w.count(), 1024);
+
+            w.pad_to_block().unwrap();
+            assert_eq!(w.padding_count(), 324);
+        }
+
+        assert_eq!(v.len(), 1024);
+        assert!(v[700..].iter().all(|b| *b == 0));
+    }
+}
@@ -0,0 +1,218 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use Counter;
+
+/// A byte-order mark, detected by a `BomReadCounter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bom {
+    /// The UTF-8 byte-order mark (`EF BB BF`).
+    Utf8,
+
+    /// The UTF-16 little-endian byte-order mark (`FF FE`).
+    Utf16Le,
+
+    /// The UTF-16 big-endian byte-order mark (`FE FF`).
+    Utf16Be,
+}
+
+const BOMS: [Bom; 3] = [Bom::Utf8, Bom::Utf16Le, Bom::Utf16Be];
+
+impl Bom {
+    /// Returns the bytes of this byte-order mark.
+    pub fn as_bytes(&self) -> &'static [u8] {
+        match *self {
+            Bom::Utf8 => &[0xEF, 0xBB, 0xBF],
+            Bom::Utf16Le => &[0xFF, 0xFE],
+            Bom::Utf16Be => &[0xFE, 0xFF],
+        }
+    }
+}
+
+/// Wraps any implementation of `std::io::Read`, detecting a byte-order mark and counting it
+/// separately from the content.
+///
+/// On the first call to `read`, a `BomReadCounter` reads enough bytes to detect a UTF-8 or UTF-16
+/// byte-order mark, calling the inner `read` as many times as needed. The byte-order mark (if any)
+/// is reported by `bom()` and counted by `bom_bytes()`, never by `count()`. By default, it is
+/// still delivered to the caller; use `with_strip()` to remove it from the stream.
+pub struct BomReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    strip: bool,
+    detected: bool,
+    bom: Option<Bom>,
+    prefix: [u8; 3],
+    prefix_len: usize,
+    pos: usize,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for BomReadCounter<'a, T> {
+    /// Creates a `BomReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> BomReadCounter<'a, T> {
+        BomReadCounter {
+            reader: value,
+            count: 0,
+            strip: false,
+            detected: false,
+            bom: None,
+            prefix: [0u8; 3],
+            prefix_len: 0,
+            pos: 0,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> BomReadCounter<'a, T> {
+    /// Removes any detected byte-order mark from the bytes delivered to the caller.
+    #[must_use]
+    pub fn with_strip(mut self) -> BomReadCounter<'a, T> {
+        self.strip = true;
+        self
+    }
+
+    /// Returns the detected byte-order mark, if any.
+    ///
+    /// This always returns `None` before the first call to `read`.
+    pub fn bom(&self) -> Option<Bom> {
+        self.bom
+    }
+
+    /// Returns the number of byte-order mark bytes read.
+    pub fn bom_bytes(&self) -> usize {
+        self.bom.map_or(0, |bom| bom.as_bytes().len())
+    }
+
+    fn detect(&mut self) -> Result<(), io::Error> {
+        loop {
+            let prefix = &self.prefix[..self.prefix_len];
+            let mut boms = BOMS.iter();
+
+            if let Some(bom) = boms.clone().find(|b| prefix.starts_with(b.as_bytes())) {
+                self.bom = Some(*bom);
+                break;
+            }
+
+            if !boms.any(|b| b.as_bytes().starts_with(prefix)) {
+                break;
+            }
+
+            match self.reader.read(&mut self.prefix[self.prefix_len..])? {
+                0 => break,
+                size => self.prefix_len += size,
+            }
+        }
+
+        self.detected = true;
+        if self.strip {
+            self.pos = self.bom_bytes();
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for BomReadCounter<'a, T> {
+    /// Returns the number of content bytes read so far, excluding any byte-order mark.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for BomReadCounter<'a, T> {
+    /// Detects any byte-order mark, then proxies to the inner `read` function, counting the bytes
+    /// read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        if !self.detected {
+            self.detect()?;
+        }
+
+        if self.pos < self.prefix_len {
+            let size = cmp::min(buffer.len(), self.prefix_len - self.pos);
+            buffer[..size].copy_from_slice(&self.prefix[self.pos..self.pos + size]);
+
+            let bom = self.bom_bytes();
+            self.count += (self.pos + size).saturating_sub(cmp::max(self.pos, bom));
+            self.pos += size;
+            return Ok(size);
+        }
+
+        let size = self.reader.read(buffer)?;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use testutil::Chunked;
+
+    #[test]
+    fn split() {
+        let mut c = Chunked(b"\xEF\xBB\xBFhi", 1);
+        let mut r = BomReadCounter::from(&mut c).with_strip();
+        let mut out = Vec::new();
+
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(&out[..], b"hi");
+        assert_eq!(r.bom(), Some(Bom::Utf8));
+        assert_eq!(r.bom_bytes(), 3);
+        assert_eq!(r.count(), 2);
+    }
+
+    #[test]
+    fn unstripped() {
+        let mut c = Chunked(b"\xFF\xFEa\x00", 3);
+        let mut r = BomReadCounter::from(&mut c);
+        let mut out = Vec::new();
+
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(&out[..], b"\xFF\xFEa\x00");
+        assert_eq!(r.bom(), Some(Bom::Utf16Le));
+        assert_eq!(r.bom_bytes(), 2);
+        assert_eq!(r.count(), 2);
+    }
+
+    #[test]
+    fn absent() {
+        let mut c = Chunked(b"\xEF\xBBx", 1);
+        let mut r = BomReadCounter::from(&mut c).with_strip();
+        let mut out = Vec::new();
+
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(&out[..], b"\xEF\xBBx");
+        assert_eq!(r.bom(), None);
+        assert_eq!(r.count(), 3);
+    }
+}
@@ -0,0 +1,251 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::BufRead` and counts the bytes consumed.
+///
+/// A `BufReadCounter` implements both `std::io::Read` and `std::io::BufRead`, so it can be used in
+/// place of the wrapped reader. Bytes are counted when they are consumed, whether by `read`,
+/// `consume` or any of the provided `BufRead` methods (such as `read_until`). In addition,
+/// `read_until_any()` reads up to the first of several delimiters and `read_cstr()` reads a
+/// NUL-terminated string.
+pub struct BufReadCounter<B: io::BufRead> {
+    reader: B,
+    count: usize,
+}
+
+impl<B: io::BufRead> From<B> for BufReadCounter<B> {
+    /// Creates a `BufReadCounter` by wrapping any implementation of `std::io::BufRead`.
+    fn from(value: B) -> BufReadCounter<B> {
+        BufReadCounter {
+            reader: value,
+            count: 0,
+        }
+    }
+}
+
+impl<B: io::BufRead> BufReadCounter<B> {
+    /// Reads bytes into `buf` up to and including the first match of any of the `delimiters`.
+    ///
+    /// The match which ends earliest in the stream wins, even when it spans the boundary between
+    /// two fills of the wrapped buffer. Only bytes appended by this call are matched, and empty
+    /// delimiters are ignored. If no delimiter is found, everything up to the end of the stream is
+    /// appended. All appended bytes are counted and their number is returned.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors (other than with the kind
+    /// `Interrupted`, which is retried). Bytes consumed before the error remain in `buf` and are
+    /// counted.
+    pub fn read_until_any(&mut self, delimiters: &[&[u8]], buf: &mut Vec<u8>) -> io::Result<usize> {
+        let start = buf.len();
+
+        loop {
+            let (used, found) = {
+                let available = match self.reader.fill_buf() {
+                    Ok(available) => available,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+
+                let mut used = 0;
+                let mut found = false;
+                for byte in available {
+                    buf.push(*byte);
+                    used += 1;
+
+                    let appended = &buf[start..];
+                    found = delimiters
+                        .iter()
+                        .any(|d| !d.is_empty() && appended.ends_with(d));
+                    if found {
+                        break;
+                    }
+                }
+
+                (used, found)
+            };
+
+            io::BufRead::consume(self, used);
+            if found || used == 0 {
+                return Ok(buf.len() - start);
+            }
+        }
+    }
+
+    /// Reads a NUL-terminated string of at most `max` bytes (excluding the terminator).
+    ///
+    /// The returned bytes do not include the terminator, but it is consumed and counted. The
+    /// string may span several fills of the wrapped buffer.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors (other than with the kind
+    /// `Interrupted`, which is retried). It will also return an error of kind `InvalidData` if
+    /// `max` bytes are read without finding a terminator, or of kind `UnexpectedEof` if the stream
+    /// ends first. Bytes consumed before the error are counted, but are discarded.
+    pub fn read_cstr(&mut self, max: usize) -> io::Result<Vec<u8>> {
+        let mut string = Vec::new();
+
+        loop {
+            let (used, done) = {
+                let available = match self.reader.fill_buf() {
+                    Ok(available) => available,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+
+                if available.is_empty() {
+                    let msg = "stream ended before the NUL terminator";
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, msg));
+                }
+
+                let room = max - string.len();
+                match available.iter().position(|byte| *byte == 0) {
+                    Some(i) if i <= room => {
+                        string.extend_from_slice(&available[..i]);
+                        (i + 1, true)
+                    }
+                    _ if room == 0 => {
+                        let msg = "C string is longer than the maximum";
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+                    }
+                    _ => {
+                        let size = room.min(available.len());
+                        string.extend_from_slice(&available[..size]);
+                        (size, false)
+                    }
+                }
+            };
+
+            io::BufRead::consume(self, used);
+            if done {
+                return Ok(string);
+            }
+        }
+    }
+
+    /// Returns a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &B {
+        &self.reader
+    }
+
+    /// Unwraps this instance, returning the wrapped reader.
+    pub fn into_inner(self) -> B {
+        self.reader
+    }
+}
+
+impl<B: io::BufRead> Counter for BufReadCounter<B> {
+    /// Returns the number of bytes consumed so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<B: io::BufRead> io::Read for BufReadCounter<B> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+impl<B: io::BufRead> io::BufRead for BufReadCounter<B> {
+    /// Proxies to the inner `fill_buf` function.
+    fn fill_buf(&mut self) -> Result<&[u8], io::Error> {
+        self.reader.fill_buf()
+    }
+
+    /// Proxies to the inner `consume` function, counting the bytes consumed.
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+        self.count += amt;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    #[test]
+    fn read_until_any() {
+        let data = b"one\r\ntwo\nthree";
+        let mut r = BufReadCounter::from(BufReader::with_capacity(4, &data[..]));
+        let delimiters: &[&[u8]] = &[b"\r\n", b"\n"];
+
+        // The `\r\n` spans two fills of the four byte buffer.
+        let mut buf = Vec::new();
+        assert_eq!(r.read_until_any(delimiters, &mut buf).unwrap(), 5);
+        assert_eq!(buf, b"one\r\n");
+
+        buf.clear();
+        assert_eq!(r.read_until_any(delimiters, &mut buf).unwrap(), 4);
+        assert_eq!(buf, b"two\n");
+
+        buf.clear();
+        assert_eq!(r.read_until_any(delimiters, &mut buf).unwrap(), 5);
+        assert_eq!(buf, b"three");
+        assert_eq!(r.read_until_any(delimiters, &mut buf).unwrap(), 0);
+        assert_eq!(r.count(), data.len());
+    }
+
+    #[test]
+    fn read_cstr() {
+        let data = b"hello\0a\0toolong";
+        let mut r = BufReadCounter::from(BufReader::with_capacity(3, &data[..]));
+
+        assert_eq!(r.read_cstr(5).unwrap(), b"hello");
+        assert_eq!(r.count(), 6);
+        assert_eq!(r.read_cstr(5).unwrap(), b"a");
+        assert_eq!(r.count(), 8);
+
+        let err = r.read_cstr(5).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(r.count(), 13);
+
+        let err = r.read_cstr(5).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn earliest() {
+        let mut r = BufReadCounter::from(&b"ab;c--d;"[..]);
+        let delimiters: &[&[u8]] = &[b"--", b";"];
+
+        let mut buf = Vec::new();
+        assert_eq!(r.read_until_any(delimiters, &mut buf).unwrap(), 3);
+        assert_eq!(r.read_until_any(delimiters, &mut buf).unwrap(), 3);
+        assert_eq!(buf, b"ab;c--");
+        assert_eq!(r.count(), 6);
+    }
+}
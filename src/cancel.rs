@@ -0,0 +1,110 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and stopping early when
+/// cancelled.
+///
+/// A `CancelableReadCounter` behaves just like a `ReadCounter`. However, before each read, it
+/// checks a shared cancellation flag. If the flag is set, `read` returns `Ok(0)` without calling
+/// the inner `read` function, so the stream appears to have ended. This lets another thread stop a
+/// long read loop (such as `io::copy`) gracefully. The count reflects only the bytes read before
+/// cancellation.
+pub struct CancelableReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    cancel: Arc<AtomicBool>,
+}
+
+impl<'a, T: 'a + io::Read> CancelableReadCounter<'a, T> {
+    /// Creates a `CancelableReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// Reading stops once `cancel` is set to `true`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, cancel: Arc<AtomicBool>) -> CancelableReadCounter<'a, T> {
+        CancelableReadCounter {
+            reader,
+            count: 0,
+            cancel,
+        }
+    }
+
+    /// Returns whether cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for CancelableReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for CancelableReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// If cancellation has been requested, this returns `Ok(0)` instead.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        if self.is_cancelled() {
+            return Ok(0);
+        }
+
+        let size = self.reader.read(buffer)?;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn cancel() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let data = [1u8, 2, 3, 4, 5];
+        let mut d = &data[..];
+        let mut r = CancelableReadCounter::new(&mut d, cancel.clone());
+        let mut buf = [0u8; 2];
+
+        assert_eq!(r.read(&mut buf).unwrap(), 2);
+        cancel.store(true, Ordering::SeqCst);
+        assert_eq!(r.read(&mut buf).unwrap(), 0);
+        assert!(r.is_cancelled());
+        assert_eq!(r.count(), 2);
+    }
+}
@@ -0,0 +1,127 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and keeping a copy of them.
+///
+/// A `CaptureReadCounter` behaves just like a `ReadCounter`. However, it also appends every byte
+/// read to an internal buffer, much like `tee` to memory. This is useful for inspecting a stream
+/// after the fact, such as when debugging. The size of the internal buffer can be bounded using
+/// `with_max_capture()`, in which case bytes beyond the bound are counted but not captured.
+pub struct CaptureReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    captured: Vec<u8>,
+    max_capture: Option<usize>,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for CaptureReadCounter<'a, T> {
+    /// Creates a `CaptureReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> CaptureReadCounter<'a, T> {
+        CaptureReadCounter {
+            reader: value,
+            count: 0,
+            captured: Vec::new(),
+            max_capture: None,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> CaptureReadCounter<'a, T> {
+    /// Limits the number of bytes captured to `max_capture`.
+    #[must_use]
+    pub fn with_max_capture(mut self, max_capture: usize) -> CaptureReadCounter<'a, T> {
+        self.captured.truncate(max_capture);
+        self.max_capture = Some(max_capture);
+        self
+    }
+
+    /// Returns the bytes captured so far.
+    pub fn captured(&self) -> &[u8] {
+        &self.captured
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for CaptureReadCounter<'a, T> {
+    /// Returns the number of bytes read so far, including any which were not captured.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for CaptureReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting and capturing the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+
+        let room = match self.max_capture {
+            Some(max) => cmp::min(size, max - self.captured.len()),
+            None => size,
+        };
+
+        self.captured.extend_from_slice(&buffer[..room]);
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn capture() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut d = &data[..];
+        let mut r = CaptureReadCounter::from(&mut d).with_max_capture(4);
+        let mut buf = [0u8; 3];
+
+        while r.read(&mut buf).unwrap() > 0 {}
+
+        assert_eq!(r.captured(), &data[..4]);
+        assert_eq!(r.count(), data.len());
+    }
+
+    #[test]
+    fn unbounded() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut d = &data[..];
+        let mut r = CaptureReadCounter::from(&mut d);
+        let mut out = Vec::new();
+
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(r.captured(), &data[..]);
+        assert_eq!(r.count(), data.len());
+    }
+}
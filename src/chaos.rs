@@ -0,0 +1,137 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and truncating each read
+/// to a pseudo-random length.
+///
+/// A `ChaosReadCounter` is a testing aid for shaking out buffer-boundary bugs in parsers. Each
+/// non-empty read is truncated to a length between one byte and the size of the buffer. Half of
+/// the reads are kept very short (at most eight bytes), since that is where boundary bugs hide.
+/// The lengths are drawn from a small xorshift generator, so a given seed always produces the
+/// same sequence. The `Read` contract still holds: `Ok(0)` is only returned at the end of the
+/// stream or for an empty buffer.
+pub struct ChaosReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    state: u64,
+}
+
+impl<'a, T: 'a + io::Read> ChaosReadCounter<'a, T> {
+    /// Creates a `ChaosReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, seed: u64) -> ChaosReadCounter<'a, T> {
+        ChaosReadCounter {
+            reader,
+            count: 0,
+            // Xorshift gets stuck at zero, so replace it with an arbitrary nonzero state.
+            state: if seed == 0 {
+                0x9e37_79b9_7f4a_7c15
+            } else {
+                seed
+            },
+        }
+    }
+
+    // Returns the next pseudo-random number (xorshift64*).
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for ChaosReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for ChaosReadCounter<'a, T> {
+    /// Proxies to the inner `read` function with a truncated buffer, counting the bytes read.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let mut max = buffer.len();
+
+        if max > 1 {
+            let random = self.next();
+            if random & 1 == 0 {
+                max = max.min(8);
+            }
+            max = 1 + (random >> 1) as usize % max;
+        }
+
+        let size = self.reader.read(&mut buffer[..max])?;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn sizes(seed: u64, data: &[u8]) -> Vec<usize> {
+        let mut d = data;
+        let mut r = ChaosReadCounter::new(&mut d, seed);
+        let mut buffer = [0u8; 64];
+        let mut sizes = Vec::new();
+        let mut output = Vec::new();
+
+        loop {
+            let size = r.read(&mut buffer).unwrap();
+            if size == 0 {
+                break;
+            }
+
+            output.extend_from_slice(&buffer[..size]);
+            sizes.push(size);
+        }
+
+        assert_eq!(output, data);
+        assert_eq!(r.count(), data.len());
+        sizes
+    }
+
+    #[test]
+    fn reproducible() {
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+
+        let first = sizes(42, &data);
+        assert_eq!(first, sizes(42, &data));
+        assert_ne!(first, sizes(7, &data));
+        assert!(first.iter().any(|size| *size <= 8));
+        assert!(first.iter().any(|size| *size > 8));
+    }
+}
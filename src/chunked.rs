@@ -0,0 +1,116 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and limiting the size of
+/// each read.
+///
+/// A `ChunkedReadCounter` never returns more than `max_chunk` bytes from a single call to `read`.
+/// It does this by passing a truncated buffer to the inner reader, so no data is lost: the next
+/// call to `read` simply continues where the previous one stopped. This gives predictable
+/// per-call sizes, which can be used to schedule reads fairly between streams.
+pub struct ChunkedReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    max_chunk: usize,
+}
+
+impl<'a, T: 'a + io::Read> ChunkedReadCounter<'a, T> {
+    /// Creates a `ChunkedReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    ///
+    /// # Panics
+    ///
+    /// If `max_chunk` is zero.
+    #[must_use]
+    pub fn new(reader: &'a mut T, max_chunk: usize) -> ChunkedReadCounter<'a, T> {
+        assert!(max_chunk > 0, "max_chunk must be nonzero");
+
+        ChunkedReadCounter {
+            reader,
+            count: 0,
+            max_chunk,
+        }
+    }
+
+    /// Returns the maximum number of bytes returned by a single `read`.
+    pub fn max_chunk(&self) -> usize {
+        self.max_chunk
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for ChunkedReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for ChunkedReadCounter<'a, T> {
+    /// Proxies to the inner `read` function with at most `max_chunk` bytes of the buffer,
+    /// counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let max = cmp::min(buffer.len(), self.max_chunk);
+        let size = self.reader.read(&mut buffer[..max])?;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn chunked() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut d = &data[..];
+        let mut r = ChunkedReadCounter::new(&mut d, 3);
+        let mut out = Vec::new();
+        let mut buf = [0u8; 8];
+
+        loop {
+            let size = r.read(&mut buf).unwrap();
+            assert!(size <= 3);
+
+            if size == 0 {
+                break;
+            }
+
+            out.extend_from_slice(&buf[..size]);
+        }
+
+        assert_eq!(out, data);
+        assert_eq!(r.count(), data.len());
+    }
+}
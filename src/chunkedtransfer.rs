@@ -0,0 +1,235 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use {Counter, TransformCounter};
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Size { size: usize, digits: usize },
+    Extension { size: usize },
+    SizeLf { size: usize },
+    Data { remaining: usize },
+    DataCr,
+    DataLf,
+    Trailer { empty: bool },
+    TrailerLf { empty: bool },
+    Done,
+}
+
+/// Wraps any implementation of `std::io::Read` containing a chunked body and counts the bytes.
+///
+/// A `ChunkedTransferReadCounter` decodes HTTP/1.1 chunked transfer encoding from the wrapped
+/// reader and yields the body. It counts both the raw bytes consumed from the wrapped reader
+/// (including the chunk-size lines, CRLFs and any trailer) and the body bytes delivered to the
+/// caller. Chunk extensions and trailer fields are consumed and discarded.
+///
+/// Framing is consumed one byte at a time, so the wrapped reader is never read past the end of
+/// the chunked body.
+pub struct ChunkedTransferReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    raw: usize,
+    body: usize,
+    state: State,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for ChunkedTransferReadCounter<'a, T> {
+    /// Creates a `ChunkedTransferReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> ChunkedTransferReadCounter<'a, T> {
+        ChunkedTransferReadCounter {
+            reader: value,
+            raw: 0,
+            body: 0,
+            state: State::Size { size: 0, digits: 0 },
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> ChunkedTransferReadCounter<'a, T> {
+    /// Returns the number of raw bytes consumed from the wrapped reader so far.
+    ///
+    /// This includes the chunk framing and any trailer.
+    pub fn raw_count(&self) -> usize {
+        self.raw
+    }
+
+    /// Returns the number of body bytes delivered to the caller so far.
+    pub fn body_count(&self) -> usize {
+        self.body
+    }
+
+    /// Returns whether the terminating zero-length chunk and trailer have been consumed.
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    // Advances the framing state machine by one byte.
+    fn step(&mut self, byte: u8) -> Result<(), io::Error> {
+        self.state = match (self.state, byte) {
+            (State::Size { size, digits }, b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F') => {
+                let digit = (byte as char).to_digit(16).unwrap() as usize;
+                let size = size
+                    .checked_mul(16)
+                    .and_then(|size| size.checked_add(digit))
+                    .ok_or_else(|| invalid("chunk size overflow"))?;
+                State::Size {
+                    size,
+                    digits: digits + 1,
+                }
+            }
+            (State::Size { digits: 0, .. }, _) => return Err(invalid("missing chunk size")),
+            (State::Size { size, .. }, b';') => State::Extension { size },
+            (State::Size { size, .. }, b'\r') => State::SizeLf { size },
+            (State::Size { .. }, _) => return Err(invalid("invalid chunk size")),
+            (State::Extension { size }, b'\r') => State::SizeLf { size },
+            (State::Extension { size }, _) => State::Extension { size },
+            (State::SizeLf { size: 0 }, b'\n') => State::Trailer { empty: true },
+            (State::SizeLf { size }, b'\n') => State::Data { remaining: size },
+            (State::DataCr, b'\r') => State::DataLf,
+            (State::DataLf, b'\n') => State::Size { size: 0, digits: 0 },
+            (State::Trailer { .. }, b'\r') => State::TrailerLf {
+                empty: self.state == State::Trailer { empty: true },
+            },
+            (State::Trailer { .. }, _) => State::Trailer { empty: false },
+            (State::TrailerLf { empty: true }, b'\n') => State::Done,
+            (State::TrailerLf { empty: false }, b'\n') => State::Trailer { empty: true },
+            _ => return Err(invalid("invalid chunk framing")),
+        };
+
+        Ok(())
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for ChunkedTransferReadCounter<'a, T> {
+    /// Returns the number of body bytes delivered to the caller so far.
+    fn count(&self) -> usize {
+        self.body
+    }
+}
+
+impl<'a, T: 'a + io::Read> TransformCounter for ChunkedTransferReadCounter<'a, T> {
+    /// Returns the number of raw bytes consumed from the wrapped reader so far.
+    fn input_count(&self) -> usize {
+        self.raw
+    }
+
+    /// Returns the number of body bytes delivered to the caller so far.
+    fn output_count(&self) -> usize {
+        self.body
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for ChunkedTransferReadCounter<'a, T> {
+    /// Reads from the inner reader, decoding the chunk framing and counting the bytes.
+    ///
+    /// This function calls the inner `read` function as many times as needed to deliver at least
+    /// one body byte or reach the end of the chunked body. Once the zero-length chunk and its
+    /// trailer have been consumed, this function returns `Ok(0)`.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors. It will also return an error
+    /// of kind `InvalidData` if the framing is malformed or of kind `UnexpectedEof` if the
+    /// wrapped reader ends before the chunked body does.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        while !buffer.is_empty() {
+            match self.state {
+                State::Done => return Ok(0),
+                State::Data { remaining } => {
+                    let max = cmp::min(remaining, buffer.len());
+                    let size = self.reader.read(&mut buffer[..max])?;
+                    if size == 0 {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+
+                    self.raw += size;
+                    self.body += size;
+                    self.state = match remaining - size {
+                        0 => State::DataCr,
+                        remaining => State::Data { remaining },
+                    };
+                    return Ok(size);
+                }
+                _ => {
+                    let mut byte = [0u8; 1];
+                    if self.reader.read(&mut byte)? == 0 {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+
+                    self.raw += 1;
+                    self.step(byte[0])?;
+                }
+            }
+        }
+
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use testutil::Chunked;
+
+    const BODY: &[u8] = b"5\r\nHello\r\n1;ext=1\r\n,\r\nC\r\n chunked wor\r\n2\r\nld\r\n0\r\nX-Foo: bar\r\n\r\nextra";
+
+    #[test]
+    fn split() {
+        let mut reader = Chunked(BODY, 3);
+        let mut counter = ChunkedTransferReadCounter::from(&mut reader);
+
+        let mut data = Vec::new();
+        counter.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"Hello, chunked world");
+        assert!(counter.is_done());
+        assert_eq!(counter.body_count(), 20);
+        assert_eq!(counter.raw_count(), BODY.len() - 5);
+        assert_eq!(counter.count(), 20);
+        assert_eq!(reader.0, b"extra");
+    }
+
+    #[test]
+    fn truncated() {
+        let mut reader = &b"5\r\nHel"[..];
+        let mut counter = ChunkedTransferReadCounter::from(&mut reader);
+
+        let mut data = Vec::new();
+        let err = counter.read_to_end(&mut data).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(data, b"Hel");
+
+        let mut reader = &b"zz\r\n"[..];
+        let mut counter = ChunkedTransferReadCounter::from(&mut reader);
+        let err = counter.read(&mut [0u8; 8]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
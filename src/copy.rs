@@ -0,0 +1,130 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use {Counter, ReadCounter, WriteCounter};
+
+/// Statistics describing a copy performed by `copy_counted()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CopyStats {
+    /// The number of bytes copied.
+    pub bytes: usize,
+
+    /// The number of successful calls to `read`, including the final one which reached the end
+    /// of the stream.
+    pub reads: usize,
+
+    /// The number of successful calls to `write`.
+    pub writes: usize,
+
+    /// The time taken by the copy.
+    pub elapsed: Duration,
+}
+
+/// Copies the entire contents of a reader into a writer, returning statistics about the copy.
+///
+/// This is like `std::io::copy`, but it returns a `CopyStats` describing the number of bytes
+/// copied, the number of calls to `read` and `write` and the time taken.
+///
+/// # Errors
+///
+/// This function will error if the underlying `read` or `write` functions error (other than with
+/// the kind `Interrupted`, which is retried) or if the writer returns that it wrote zero bytes.
+pub fn copy_counted<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<CopyStats, io::Error> {
+    let start = Instant::now();
+    let mut reader = ReadCounter::from(reader);
+    let mut writer = WriteCounter::from(writer);
+    let mut buffer = [0u8; 8192];
+
+    loop {
+        let size = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(size) => size,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        writer.write_all(&buffer[..size])?;
+    }
+
+    Ok(CopyStats {
+        bytes: writer.count(),
+        reads: reader.operations(),
+        writes: writer.operations(),
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Reads a reader to the end, discarding the data and returning the number of bytes read.
+///
+/// The reader is taken by value, so this works directly on temporaries (such as
+/// `drain_count(File::open(path)?)`). Pass `&mut reader` to keep using the reader afterwards.
+///
+/// # Errors
+///
+/// This function will error if the underlying `read` function errors (other than with the kind
+/// `Interrupted`, which is retried).
+pub fn drain_count<R: Read>(mut reader: R) -> Result<usize, io::Error> {
+    let mut reader = ReadCounter::from(&mut reader);
+    let mut buffer = [0u8; 8192];
+
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(reader.count()),
+            Ok(_) => continue,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testutil::{Chunked, Short};
+
+    #[test]
+    fn copy() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut d = &data[..];
+        let mut s = Short(Vec::new(), 4);
+
+        let stats = copy_counted(&mut d, &mut s).unwrap();
+        assert_eq!(stats.bytes, 10);
+        assert_eq!(stats.reads, 2);
+        assert_eq!(stats.writes, 3);
+        assert_eq!(s.0, data);
+    }
+
+    #[test]
+    fn drain() {
+        let data = vec![7u8; 20_000];
+        assert_eq!(drain_count(io::Cursor::new(&data)).unwrap(), 20_000);
+        assert_eq!(drain_count(Chunked(&data[..10], 3)).unwrap(), 10);
+
+        let mut d = &data[..5];
+        assert_eq!(drain_count(&mut d).unwrap(), 5);
+        assert!(d.is_empty());
+    }
+}
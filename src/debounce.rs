@@ -0,0 +1,110 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use Counter;
+
+/// Wraps any implementation of `Counter`, refreshing its count at most once per interval.
+///
+/// A `DebouncedCounter` caches the count of the wrapped counter. A call to `count()` only queries
+/// the wrapped counter again once `interval` has elapsed since the cached value was taken;
+/// otherwise it returns the cached value. This smooths out high-frequency polling, such as
+/// logging the count from a tight loop. The first call always queries the wrapped counter.
+pub struct DebouncedCounter<C: Counter> {
+    inner: C,
+    interval: Duration,
+    cached: Cell<Option<(usize, Instant)>>,
+}
+
+impl<C: Counter> DebouncedCounter<C> {
+    /// Creates a `DebouncedCounter` by wrapping any implementation of `Counter`.
+    #[must_use]
+    pub fn new(inner: C, interval: Duration) -> DebouncedCounter<C> {
+        DebouncedCounter {
+            inner,
+            interval,
+            cached: Cell::new(None),
+        }
+    }
+
+    /// Returns a reference to the wrapped counter.
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped counter.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Unwraps this instance, returning the wrapped counter.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn count_at(&self, now: Instant) -> usize {
+        match self.cached.get() {
+            Some((count, at)) if now.duration_since(at) < self.interval => count,
+            _ => {
+                let count = self.inner.count();
+                self.cached.set(Some((count, now)));
+                count
+            }
+        }
+    }
+}
+
+impl<C: Counter> Counter for DebouncedCounter<C> {
+    /// Returns the cached count, refreshing it from the wrapped counter if the interval elapsed.
+    fn count(&self) -> usize {
+        self.count_at(Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testutil::Fixed;
+
+    #[test]
+    fn debounce() {
+        let mut d = DebouncedCounter::new(Fixed(1), Duration::from_millis(50));
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        assert_eq!(d.count_at(at(0)), 1);
+
+        for i in 2..100 {
+            d.get_mut().0 = i;
+            assert_eq!(d.count_at(at(i as u64 / 4)), 1);
+        }
+
+        assert_eq!(d.count_at(at(50)), 99);
+        d.get_mut().0 = 100;
+        assert_eq!(d.count_at(at(99)), 99);
+        assert_eq!(d.count_at(at(100)), 100);
+    }
+
+    #[test]
+    fn first() {
+        let d = DebouncedCounter::new(Fixed(7), Duration::from_secs(3600));
+        assert_eq!(d.count(), 7);
+        assert_eq!(d.into_inner().0, 7);
+    }
+}
@@ -0,0 +1,137 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and the records separated
+/// by a delimiter byte.
+///
+/// A `DelimitedReadCounter` behaves just like a `ReadCounter`. However, it also scans the bytes
+/// read for a delimiter (such as `b'\n'` or `0`), counting each one as the end of a record and
+/// tracking record lengths. Records may span any number of reads. Record lengths exclude the
+/// delimiter itself.
+pub struct DelimitedReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    delimiter: u8,
+    records: usize,
+    current: usize,
+    last: Option<usize>,
+}
+
+impl<'a, T: 'a + io::Read> DelimitedReadCounter<'a, T> {
+    /// Creates a `DelimitedReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, delimiter: u8) -> DelimitedReadCounter<'a, T> {
+        DelimitedReadCounter {
+            reader,
+            count: 0,
+            delimiter,
+            records: 0,
+            current: 0,
+            last: None,
+        }
+    }
+
+    /// Returns the number of complete (delimited) records read so far.
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    /// Returns the length of the record currently in progress.
+    pub fn current_record_len(&self) -> usize {
+        self.current
+    }
+
+    /// Returns the length of the most recent complete record, if any.
+    pub fn last_record_len(&self) -> Option<usize> {
+        self.last
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for DelimitedReadCounter<'a, T> {
+    /// Returns the number of bytes read so far, including delimiters.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for DelimitedReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes and records read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+
+        for byte in &buffer[..size] {
+            if *byte == self.delimiter {
+                self.last = Some(self.current);
+                self.records += 1;
+                self.current = 0;
+            } else {
+                self.current += 1;
+            }
+        }
+
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn records() {
+        let data = b"abc\nde\n\nfghij";
+        let mut d = &data[..];
+        let mut r = DelimitedReadCounter::new(&mut d, b'\n');
+        let mut buf = [0u8; 2];
+
+        assert_eq!(r.read(&mut buf).unwrap(), 2);
+        assert_eq!((r.records(), r.current_record_len()), (0, 2));
+        assert_eq!(r.last_record_len(), None);
+
+        assert_eq!(r.read(&mut buf).unwrap(), 2);
+        assert_eq!((r.records(), r.current_record_len()), (1, 0));
+        assert_eq!(r.last_record_len(), Some(3));
+
+        r.read_exact(&mut buf).unwrap();
+        r.read_exact(&mut buf).unwrap();
+        assert_eq!((r.records(), r.current_record_len()), (3, 0));
+        assert_eq!(r.last_record_len(), Some(0));
+
+        let mut rest = Vec::new();
+        r.read_to_end(&mut rest).unwrap();
+        assert_eq!((r.records(), r.current_record_len()), (3, 5));
+        assert_eq!(r.count(), data.len());
+    }
+}
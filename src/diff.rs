@@ -0,0 +1,121 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Write`, counting the bytes written and those which
+/// differ from a baseline.
+///
+/// A `DiffWriteCounter` behaves just like a `WriteCounter`. However, it also compares each byte
+/// accepted by the wrapped writer with the baseline byte at the same offset. Bytes which differ
+/// are counted by `diff_count()`, as are any bytes written past the end of the baseline. This
+/// measures how much of an output diverges from a previous version, such as when generating a
+/// patch.
+pub struct DiffWriteCounter<'a, T: 'a + io::Write> {
+    writer: &'a mut T,
+    baseline: &'a [u8],
+    count: usize,
+    diff: usize,
+}
+
+impl<'a, T: 'a + io::Write> DiffWriteCounter<'a, T> {
+    /// Creates a `DiffWriteCounter` by wrapping any implementation of `std::io::Write`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance
+    /// or the baseline.
+    #[must_use]
+    pub fn new(writer: &'a mut T, baseline: &'a [u8]) -> DiffWriteCounter<'a, T> {
+        DiffWriteCounter {
+            writer,
+            baseline,
+            count: 0,
+            diff: 0,
+        }
+    }
+
+    /// Returns the number of bytes written so far which differ from the baseline.
+    ///
+    /// Bytes written past the end of the baseline always differ. Baseline bytes which have not
+    /// been overwritten (because the output is shorter) are not included.
+    pub fn diff_count(&self) -> usize {
+        self.diff
+    }
+}
+
+impl<'a, T: 'a + io::Write> Counter for DiffWriteCounter<'a, T> {
+    /// Returns the total number of bytes written so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Write> io::Write for DiffWriteCounter<'a, T> {
+    /// Proxies to the inner `write` function, comparing and counting the bytes written.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        let size = self.writer.write(buffer)?;
+
+        let start = self.count.min(self.baseline.len());
+        let end = (self.count + size).min(self.baseline.len());
+        let baseline = &self.baseline[start..end];
+
+        let differing = buffer.iter().zip(baseline).filter(|(a, b)| a != b).count();
+        self.diff += differing + size - baseline.len();
+        self.count += size;
+        Ok(size)
+    }
+
+    /// Proxies to the inner `flush` function.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn diff() {
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = DiffWriteCounter::new(&mut v, b"hello world");
+
+        w.write_all(b"help").unwrap();
+        assert_eq!(w.diff_count(), 1);
+
+        w.write_all(b"o wo").unwrap();
+        assert_eq!(w.diff_count(), 1);
+
+        // The last byte extends past the baseline, so it differs too.
+        w.write_all(b"rmz!").unwrap();
+        assert_eq!(w.diff_count(), 4);
+        assert_eq!(w.count(), 12);
+        assert_eq!(v, b"helpo wormz!");
+    }
+}
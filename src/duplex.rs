@@ -0,0 +1,134 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of both `std::io::Read` and `std::io::Write`, counting the bytes read
+/// and written separately.
+///
+/// A stream such as a `TcpStream` is both readable and writable. Wrapping it in both a
+/// `ReadCounter` and a `WriteCounter` is impossible, since each requires a mutable borrow. A
+/// `DuplexCounter` solves this by implementing both traits on a single wrapper.
+pub struct DuplexCounter<'a, T: 'a + io::Read + io::Write> {
+    stream: &'a mut T,
+    read: usize,
+    written: usize,
+}
+
+impl<'a, T: 'a + io::Read + io::Write> From<&'a mut T> for DuplexCounter<'a, T> {
+    /// Creates a `DuplexCounter` by wrapping any implementation of both `std::io::Read` and
+    /// `std::io::Write`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> DuplexCounter<'a, T> {
+        DuplexCounter {
+            stream: value,
+            read: 0,
+            written: 0,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read + io::Write> DuplexCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    pub fn read_count(&self) -> usize {
+        self.read
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn write_count(&self) -> usize {
+        self.written
+    }
+}
+
+impl<'a, T: 'a + io::Read + io::Write> Counter for DuplexCounter<'a, T> {
+    /// Returns the total number of bytes read and written so far.
+    fn count(&self) -> usize {
+        self.read + self.written
+    }
+}
+
+impl<'a, T: 'a + io::Read + io::Write> io::Read for DuplexCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.stream.read(buffer)?;
+        self.read += size;
+        Ok(size)
+    }
+}
+
+impl<'a, T: 'a + io::Read + io::Write> io::Write for DuplexCounter<'a, T> {
+    /// Proxies to the inner `write` function, counting the bytes written along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        let size = self.stream.write(buffer)?;
+        self.written += size;
+        Ok(size)
+    }
+
+    /// Proxies to the inner `flush` function.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use testutil::Duplex;
+
+    #[test]
+    fn duplex() {
+        let mut s = Duplex::new(b"request", usize::MAX);
+
+        {
+            let mut d = DuplexCounter::from(&mut s);
+            let mut buf = [0u8; 4];
+
+            assert_eq!(d.read(&mut buf).unwrap(), 4);
+            d.write_all(b"response!").unwrap();
+            assert_eq!(d.read(&mut buf).unwrap(), 3);
+
+            assert_eq!(d.read_count(), 7);
+            assert_eq!(d.write_count(), 9);
+            assert_eq!(d.count(), 16);
+        }
+
+        assert_eq!(&s.output[..], b"response!");
+    }
+}
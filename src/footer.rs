@@ -0,0 +1,223 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::io;
+
+use crc32;
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting content bytes and checking them against a
+/// trailing checksum footer.
+///
+/// A `FooterCheckReadCounter` passes every byte through to the caller. However, it treats the last
+/// `footer_len` bytes of the stream as a footer rather than content. Since the end of the stream is
+/// not known in advance, the most recent `footer_len` bytes are held back from the count and the
+/// running CRC-32 until more data arrives. Once the stream is exhausted, `count()` and `checksum()`
+/// cover exactly the content and `footer()` holds the footer.
+pub struct FooterCheckReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    crc: u32,
+    footer: VecDeque<u8>,
+    footer_len: usize,
+    eof: bool,
+}
+
+impl<'a, T: 'a + io::Read> FooterCheckReadCounter<'a, T> {
+    /// Creates a `FooterCheckReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, footer_len: usize) -> FooterCheckReadCounter<'a, T> {
+        FooterCheckReadCounter {
+            reader,
+            count: 0,
+            crc: 0,
+            footer: VecDeque::with_capacity(footer_len),
+            footer_len,
+            eof: false,
+        }
+    }
+
+    /// Returns the CRC-32 checksum of the content bytes read so far.
+    pub fn checksum(&self) -> u32 {
+        self.crc
+    }
+
+    /// Returns the trailing bytes currently held back as the footer.
+    ///
+    /// This is only the actual footer once the stream has been read to the end.
+    pub fn footer(&self) -> Vec<u8> {
+        self.footer.iter().cloned().collect()
+    }
+
+    /// Compares the content checksum against `expected`.
+    ///
+    /// The stream must already have been read to the end, since only then is the footer known.
+    /// This does not read from the stream itself.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error of kind `InvalidInput` if the stream has not yet been
+    /// read to the end, of kind `UnexpectedEof` if the stream is shorter than the footer, or of
+    /// kind `InvalidData` if the checksum does not match.
+    pub fn verify_footer(&self, expected: u32) -> io::Result<()> {
+        if !self.eof {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "stream has not been read to the end",
+            ));
+        }
+
+        if self.footer.len() < self.footer_len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stream ended before the footer",
+            ));
+        }
+
+        if self.crc != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "content checksum does not match the footer",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, buffer: &[u8]) {
+        // The bytes pushed out of the window are content: first the oldest held back bytes, then
+        // the start of `buffer`.
+        let excess = (self.footer.len() + buffer.len()).saturating_sub(self.footer_len);
+        let held = cmp::min(excess, self.footer.len());
+
+        let (front, back) = self.footer.as_slices();
+        let split = cmp::min(held, front.len());
+        self.crc = crc32::update(self.crc, &front[..split]);
+        self.crc = crc32::update(self.crc, &back[..held - split]);
+        self.footer.drain(..held);
+
+        self.crc = crc32::update(self.crc, &buffer[..excess - held]);
+        self.footer.extend(&buffer[excess - held..]);
+        self.count += excess;
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for FooterCheckReadCounter<'a, T> {
+    /// Returns the number of content bytes read so far, excluding the held back footer.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for FooterCheckReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the content bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+        if size == 0 && !buffer.is_empty() {
+            self.eof = true;
+        }
+
+        self.update(&buffer[..size]);
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use testutil::Chunked;
+
+    fn framed(content: &[u8], crc: u32) -> Vec<u8> {
+        let mut data = content.to_vec();
+        data.extend_from_slice(&crc.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn matching() {
+        let data = framed(b"123456789", 0xCBF4_3926);
+        let mut d = &data[..];
+        let mut r = FooterCheckReadCounter::new(&mut d, 4);
+        let mut buf = [0u8; 5];
+
+        assert_eq!(r.read(&mut buf).unwrap(), 5);
+        assert_eq!(r.count(), 1);
+        let err = r.verify_footer(0xCBF4_3926).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let mut rest = Vec::new();
+        assert_eq!(r.read_to_end(&mut rest).unwrap(), 8);
+        r.verify_footer(0xCBF4_3926).unwrap();
+        assert_eq!(r.count(), 9);
+        assert_eq!(r.footer(), vec![0xCB, 0xF4, 0x39, 0x26]);
+    }
+
+    #[test]
+    fn mismatching() {
+        let data = framed(b"123456780", 0xCBF4_3926);
+        let mut d = &data[..];
+        let mut r = FooterCheckReadCounter::new(&mut d, 4);
+        io::copy(&mut r, &mut io::sink()).unwrap();
+
+        let err = r.verify_footer(0xCBF4_3926).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(r.count(), 9);
+    }
+
+    #[test]
+    fn short() {
+        let mut d = &b"12"[..];
+        let mut r = FooterCheckReadCounter::new(&mut d, 4);
+        io::copy(&mut r, &mut io::sink()).unwrap();
+
+        let err = r.verify_footer(0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(r.count(), 0);
+    }
+
+    #[test]
+    fn chunked() {
+        let data = framed(b"123456789", 0xCBF4_3926);
+
+        for chunk in 1..data.len() + 1 {
+            let mut c = Chunked(&data, chunk);
+            let mut r = FooterCheckReadCounter::new(&mut c, 4);
+            let mut out = Vec::new();
+
+            r.read_to_end(&mut out).unwrap();
+            r.verify_footer(0xCBF4_3926).unwrap();
+            assert_eq!(out, data);
+            assert_eq!(r.count(), 9);
+            assert_eq!(r.footer(), vec![0xCB, 0xF4, 0x39, 0x26]);
+        }
+    }
+}
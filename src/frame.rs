@@ -0,0 +1,147 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use std::io::Read;
+
+use Counter;
+
+fn truncated() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "stream ended in the middle of a frame",
+    )
+}
+
+/// Wraps any implementation of `std::io::Read` and reads length-prefixed frames from it.
+///
+/// Each frame consists of a big-endian `u32` length followed by that many bytes of payload. A
+/// `FrameReadCounter` counts all of the bytes consumed from the wrapped reader, including the
+/// length prefixes.
+pub struct FrameReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for FrameReadCounter<'a, T> {
+    /// Creates a `FrameReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> FrameReadCounter<'a, T> {
+        FrameReadCounter {
+            reader: value,
+            count: 0,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> FrameReadCounter<'a, T> {
+    /// Reads the next frame, returning its payload.
+    ///
+    /// Returns `Ok(None)` if the stream ends cleanly at a frame boundary.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `next_frame`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors. It will also return an error
+    /// of kind `UnexpectedEof` if the stream ends in the middle of a frame. Any bytes consumed
+    /// before the error are still counted.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, io::Error> {
+        let mut prefix = [0u8; 4];
+        let mut size = 0;
+
+        while size < prefix.len() {
+            match self.reader.read(&mut prefix[size..]) {
+                Ok(0) => break,
+                Ok(n) => size += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.count += size;
+                    return Err(e);
+                }
+            }
+        }
+
+        self.count += size;
+        match size {
+            0 => return Ok(None),
+            4 => {}
+            _ => return Err(truncated()),
+        }
+
+        let length = u64::from(u32::from_be_bytes(prefix));
+        let mut payload = Vec::new();
+        let result = (&mut *self.reader).take(length).read_to_end(&mut payload);
+        self.count += payload.len();
+        result?;
+
+        if payload.len() as u64 != length {
+            return Err(truncated());
+        }
+
+        Ok(Some(payload))
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for FrameReadCounter<'a, T> {
+    /// Returns the number of bytes read so far, including the length prefixes.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames() {
+        let data = [0u8, 0, 0, 2, 1, 2, 0, 0, 0, 0, 0, 0, 0, 1, 3];
+        let mut d = &data[..];
+        let mut r = FrameReadCounter::from(&mut d);
+
+        assert_eq!(r.next_frame().unwrap(), Some(vec![1, 2]));
+        assert_eq!(r.count(), 6);
+        assert_eq!(r.next_frame().unwrap(), Some(vec![]));
+        assert_eq!(r.next_frame().unwrap(), Some(vec![3]));
+        assert_eq!(r.next_frame().unwrap(), None);
+        assert_eq!(r.count(), data.len());
+    }
+
+    #[test]
+    fn truncated() {
+        let data = [0u8, 0, 0, 1, 9, 0, 0, 0, 3, 1, 2];
+        let mut d = &data[..];
+        let mut r = FrameReadCounter::from(&mut d);
+
+        assert_eq!(r.next_frame().unwrap(), Some(vec![9]));
+        let err = r.next_frame().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(r.count(), data.len());
+
+        let data = [0u8, 0];
+        let mut d = &data[..];
+        let mut r = FrameReadCounter::from(&mut d);
+        let err = r.next_frame().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,143 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::convert::TryFrom;
+use std::io;
+use std::io::Write;
+use std::mem;
+
+use {Counter, WriteCounter};
+
+/// Wraps any implementation of `std::io::Write`, framing messages with a length prefix.
+///
+/// A `FramedWriteCounter` buffers everything written to it as the body of the current message.
+/// When `finish_frame()` is called, the body is written to the wrapped writer preceded by its
+/// length as a 4-byte big-endian integer. The count includes both the prefixes and the bodies
+/// written to the wrapped writer; bytes which are still buffered are not counted.
+pub struct FramedWriteCounter<'a, T: 'a + io::Write> {
+    writer: &'a mut T,
+    count: usize,
+    frames: usize,
+    body: Vec<u8>,
+}
+
+impl<'a, T: 'a + io::Write> From<&'a mut T> for FramedWriteCounter<'a, T> {
+    /// Creates a `FramedWriteCounter` by wrapping any implementation of `std::io::Write`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> FramedWriteCounter<'a, T> {
+        FramedWriteCounter {
+            writer: value,
+            count: 0,
+            frames: 0,
+            body: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Write> FramedWriteCounter<'a, T> {
+    /// Writes the length prefix and the buffered body of the current message, then flushes.
+    ///
+    /// An empty message produces a frame consisting of only the prefix.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying writer errors. It will also return an error of
+    /// kind `InvalidInput` if the body is longer than `u32::max_value()` bytes. If an error
+    /// occurs, the body is discarded and the count only includes the bytes actually written.
+    pub fn finish_frame(&mut self) -> io::Result<()> {
+        let body = mem::take(&mut self.body);
+        let len = u32::try_from(body.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too long"))?;
+
+        let mut counting = WriteCounter::from(&mut *self.writer);
+        let result = counting
+            .write_all(&len.to_be_bytes())
+            .and_then(|_| counting.write_all(&body))
+            .and_then(|_| counting.flush());
+        self.count += counting.count();
+
+        result?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Returns the number of frames written so far.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Returns the number of body bytes buffered for the current message.
+    pub fn pending(&self) -> usize {
+        self.body.len()
+    }
+}
+
+impl<'a, T: 'a + io::Write> Counter for FramedWriteCounter<'a, T> {
+    /// Returns the number of bytes written to the wrapped writer so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Write> io::Write for FramedWriteCounter<'a, T> {
+    /// Appends the buffer to the body of the current message.
+    ///
+    /// Nothing is written to the wrapped writer until `finish_frame()` is called.
+    ///
+    /// # Errors
+    ///
+    /// This function never errors.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        self.body.extend_from_slice(buffer);
+        Ok(buffer.len())
+    }
+
+    /// Proxies to the inner `flush` function.
+    ///
+    /// This does not finish the current message.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames() {
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = FramedWriteCounter::from(&mut v);
+
+        w.write_all(b"hel").unwrap();
+        w.write_all(b"lo").unwrap();
+        assert_eq!(w.count(), 0);
+        assert_eq!(w.pending(), 5);
+        w.finish_frame().unwrap();
+        assert_eq!(w.count(), 9);
+
+        w.write_all(b"!").unwrap();
+        w.finish_frame().unwrap();
+        assert_eq!(w.count(), 14);
+        assert_eq!(w.frames(), 2);
+        assert_eq!(w.pending(), 0);
+
+        assert_eq!(v, b"\x00\x00\x00\x05hello\x00\x00\x00\x01!");
+    }
+}
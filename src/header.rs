@@ -0,0 +1,123 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, skipping a fixed-size header and counting the
+/// header and body bytes separately.
+///
+/// A `HeaderSkipReadCounter` discards the first `header_len` bytes of the stream. The first call
+/// to `read` consumes the header (calling the inner `read` as many times as needed), then reads
+/// the body. Only body bytes are delivered to the caller and included in `count()`. If the stream
+/// ends within the header, `read` returns `Ok(0)`; use `header_bytes()` to detect this.
+pub struct HeaderSkipReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    header: usize,
+    header_len: usize,
+}
+
+impl<'a, T: 'a + io::Read> HeaderSkipReadCounter<'a, T> {
+    /// Creates a `HeaderSkipReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, header_len: usize) -> HeaderSkipReadCounter<'a, T> {
+        HeaderSkipReadCounter {
+            reader,
+            count: 0,
+            header: 0,
+            header_len,
+        }
+    }
+
+    /// Returns the number of header bytes consumed so far.
+    pub fn header_bytes(&self) -> usize {
+        self.header
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for HeaderSkipReadCounter<'a, T> {
+    /// Returns the number of body bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for HeaderSkipReadCounter<'a, T> {
+    /// Skips any remaining header, then proxies to the inner `read` function, counting the bytes
+    /// read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let mut scratch = [0u8; 512];
+
+        while self.header < self.header_len {
+            let max = cmp::min(scratch.len(), self.header_len - self.header);
+
+            match self.reader.read(&mut scratch[..max])? {
+                0 => return Ok(0),
+                size => self.header += size,
+            }
+        }
+
+        let size = self.reader.read(buffer)?;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use testutil::Chunked;
+
+    #[test]
+    fn skip() {
+        let mut c = Chunked(b"HEADERbody", 4);
+        let mut r = HeaderSkipReadCounter::new(&mut c, 6);
+        let mut out = Vec::new();
+
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(&out[..], b"body");
+        assert_eq!(r.header_bytes(), 6);
+        assert_eq!(r.count(), 4);
+    }
+
+    #[test]
+    fn short() {
+        let mut d: &[u8] = b"HEAD";
+        let mut r = HeaderSkipReadCounter::new(&mut d, 6);
+        let mut buf = [0u8; 8];
+
+        assert_eq!(r.read(&mut buf).unwrap(), 0);
+        assert_eq!(r.header_bytes(), 4);
+        assert_eq!(r.count(), 0);
+    }
+}
@@ -0,0 +1,179 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use {Counter, TransformCounter};
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Wraps any implementation of `std::io::Read` containing hex and counts the bytes decoded.
+///
+/// A `HexReadCounter` reads base16 text (in either case) from the wrapped reader and yields the
+/// decoded bytes. It counts both the encoded bytes consumed from the wrapped reader and the
+/// decoded bytes delivered to the caller. Digit pairs which are split across reads are buffered
+/// internally. ASCII whitespace is skipped wherever it appears, even between the two digits of a
+/// pair.
+pub struct HexReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    encoded: usize,
+    decoded: usize,
+    nibble: Option<u8>,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for HexReadCounter<'a, T> {
+    /// Creates a `HexReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> HexReadCounter<'a, T> {
+        HexReadCounter {
+            reader: value,
+            encoded: 0,
+            decoded: 0,
+            nibble: None,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> HexReadCounter<'a, T> {
+    /// Returns the number of encoded bytes consumed from the wrapped reader so far.
+    ///
+    /// This includes any whitespace.
+    pub fn encoded_count(&self) -> usize {
+        self.encoded
+    }
+
+    /// Returns the number of decoded bytes delivered to the caller so far.
+    pub fn decoded_count(&self) -> usize {
+        self.decoded
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for HexReadCounter<'a, T> {
+    /// Returns the number of decoded bytes delivered to the caller so far.
+    fn count(&self) -> usize {
+        self.decoded
+    }
+}
+
+impl<'a, T: 'a + io::Read> TransformCounter for HexReadCounter<'a, T> {
+    /// Returns the number of encoded bytes consumed from the wrapped reader so far.
+    fn input_count(&self) -> usize {
+        self.encoded
+    }
+
+    /// Returns the number of decoded bytes delivered to the caller so far.
+    fn output_count(&self) -> usize {
+        self.decoded
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for HexReadCounter<'a, T> {
+    /// Reads from the inner reader, decoding hex and counting the bytes along the way.
+    ///
+    /// This function calls the inner `read` function as many times as needed to decode at least
+    /// one byte or reach the end of the stream.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors. It will also return an error
+    /// of kind `InvalidData` if the stream contains a character which is neither a hex digit nor
+    /// whitespace, or if it ends with an odd trailing digit.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let mut pos = 0;
+        let mut scratch = [0u8; 1024];
+
+        // Bound the input so that the decoded bytes always fit in the buffer.
+        let max = cmp::min(scratch.len(), 2 * buffer.len());
+
+        while pos == 0 && !buffer.is_empty() {
+            let size = self.reader.read(&mut scratch[..max])?;
+            self.encoded += size;
+
+            if size == 0 {
+                if self.nibble.is_some() {
+                    return Err(invalid("odd number of hex digits"));
+                }
+                break;
+            }
+
+            for byte in &scratch[..size] {
+                let value = match byte {
+                    b' ' | b'\t' | b'\r' | b'\n' => continue,
+                    _ => match (*byte as char).to_digit(16) {
+                        Some(value) => value as u8,
+                        None => return Err(invalid("invalid hex character")),
+                    },
+                };
+
+                match self.nibble.take() {
+                    None => self.nibble = Some(value),
+                    Some(high) => {
+                        buffer[pos] = high << 4 | value;
+                        pos += 1;
+                    }
+                }
+            }
+        }
+
+        self.decoded += pos;
+        Ok(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use testutil::Chunked;
+
+    #[test]
+    fn split() {
+        let encoded = b"48656C6c6f2c\n 20776f726c64";
+        let mut reader = Chunked(encoded, 3);
+        let mut counter = HexReadCounter::from(&mut reader);
+
+        let mut data = Vec::new();
+        counter.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"Hello, world");
+        assert_eq!(counter.encoded_count(), encoded.len());
+        assert_eq!(counter.decoded_count(), 12);
+        assert_eq!(counter.count(), 12);
+    }
+
+    #[test]
+    fn errors() {
+        let mut reader = &b"abc"[..];
+        let mut counter = HexReadCounter::from(&mut reader);
+        let err = counter.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut reader = &b"0g"[..];
+        let mut counter = HexReadCounter::from(&mut reader);
+        let err = counter.read(&mut [0u8; 4]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,115 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and tracking how long the
+/// stream has been idle.
+///
+/// An `IdleReadCounter` behaves just like a `ReadCounter`. However, it also records when the last
+/// non-empty read happened. Until the first such read, the creation time is used instead. This
+/// can be used to detect stalled connections.
+pub struct IdleReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    last: Instant,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for IdleReadCounter<'a, T> {
+    /// Creates an `IdleReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> IdleReadCounter<'a, T> {
+        IdleReadCounter {
+            reader: value,
+            count: 0,
+            last: Instant::now(),
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> IdleReadCounter<'a, T> {
+    /// Returns the time elapsed since the last non-empty read.
+    pub fn idle_for(&self) -> Duration {
+        self.last.elapsed()
+    }
+
+    /// Returns whether no bytes have been read for at least `threshold`.
+    pub fn is_idle(&self, threshold: Duration) -> bool {
+        self.idle_for() >= threshold
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for IdleReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for IdleReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+
+        if size > 0 {
+            self.last = Instant::now();
+        }
+
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::thread::sleep;
+
+    #[test]
+    fn idle() {
+        let data = [1u8, 2, 3];
+        let mut d = &data[..];
+        let mut r = IdleReadCounter::from(&mut d);
+        let mut buf = [0u8; 8];
+
+        assert_eq!(r.read(&mut buf).unwrap(), 3);
+        let before = r.idle_for();
+
+        sleep(Duration::from_millis(20));
+        assert_eq!(r.read(&mut buf).unwrap(), 0);
+
+        assert!(r.idle_for() >= before + Duration::from_millis(20));
+        assert!(r.is_idle(Duration::from_millis(20)));
+        assert!(!r.is_idle(Duration::from_secs(3600)));
+        assert_eq!(r.count(), 3);
+    }
+}
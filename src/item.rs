@@ -0,0 +1,82 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use Counter;
+
+/// Wraps any implementation of `Iterator` and counts the items yielded.
+///
+/// An `ItemCounter` passes through every item of the wrapped iterator, counting them along the
+/// way. Since any `&mut I` is also an iterator, it can wrap either an owned iterator or a borrowed
+/// one.
+///
+/// Since `Iterator` also has a `count` method (which consumes the iterator), call
+/// `Counter::count(&counter)` to get the number of items yielded so far.
+pub struct ItemCounter<I: Iterator> {
+    iter: I,
+    count: usize,
+}
+
+impl<I: Iterator> From<I> for ItemCounter<I> {
+    /// Creates an `ItemCounter` by wrapping any implementation of `Iterator`.
+    fn from(value: I) -> ItemCounter<I> {
+        ItemCounter {
+            iter: value,
+            count: 0,
+        }
+    }
+}
+
+impl<I: Iterator> Counter for ItemCounter<I> {
+    /// Returns the number of items yielded so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<I: Iterator> Iterator for ItemCounter<I> {
+    type Item = I::Item;
+
+    /// Proxies to the inner `next` function, counting the items yielded along the way.
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.iter.next()?;
+        self.count += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items() {
+        let mut i = ItemCounter::from(0..10);
+
+        assert_eq!(i.by_ref().take(4).sum::<i32>(), 6);
+        assert_eq!(Counter::count(&i), 4);
+        assert_eq!(i.next(), Some(4));
+        assert_eq!(Counter::count(&i), 5);
+
+        assert_eq!(i.by_ref().last(), Some(9));
+        assert_eq!(Counter::count(&i), 10);
+    }
+}
@@ -0,0 +1,158 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and measuring the jitter
+/// between reads.
+///
+/// A `JitterReadCounter` behaves just like a `ReadCounter`. However, it also records the gaps
+/// between successive non-empty reads. The jitter is the standard deviation of those gaps, which
+/// surfaces timing irregularities in a stream. The running variance is computed with Welford's
+/// algorithm, so it stays numerically stable over long streams.
+pub struct JitterReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    last: Option<Instant>,
+    gaps: usize,
+    mean: f64,
+    m2: f64,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for JitterReadCounter<'a, T> {
+    /// Creates a `JitterReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> JitterReadCounter<'a, T> {
+        JitterReadCounter {
+            reader: value,
+            count: 0,
+            last: None,
+            gaps: 0,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> JitterReadCounter<'a, T> {
+    /// Returns the mean gap between successive non-empty reads.
+    ///
+    /// This is zero until at least two non-empty reads have completed.
+    pub fn mean_gap(&self) -> Duration {
+        Duration::from_secs_f64(self.mean)
+    }
+
+    /// Returns the standard deviation of the gaps between successive non-empty reads.
+    ///
+    /// This is zero until at least two gaps (three non-empty reads) have been measured.
+    pub fn jitter(&self) -> Duration {
+        if self.gaps < 2 {
+            return Duration::from_secs(0);
+        }
+
+        Duration::from_secs_f64((self.m2 / self.gaps as f64).sqrt())
+    }
+
+    fn update(&mut self, now: Instant) {
+        if let Some(last) = self.last {
+            let gap = now.duration_since(last).as_secs_f64();
+
+            self.gaps += 1;
+            let delta = gap - self.mean;
+            self.mean += delta / self.gaps as f64;
+            self.m2 += delta * (gap - self.mean);
+        }
+
+        self.last = Some(now);
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for JitterReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for JitterReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+
+        if size > 0 {
+            self.update(Instant::now());
+        }
+
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn reads() {
+        let data = [0u8; 4];
+        let mut d = &data[..];
+        let mut r = JitterReadCounter::from(&mut d);
+        let mut buf = [0u8; 1];
+
+        for _ in 0..4 {
+            assert_eq!(r.read(&mut buf).unwrap(), 1);
+        }
+
+        assert_eq!(r.read(&mut buf).unwrap(), 0);
+        assert_eq!(r.gaps, 3);
+        assert_eq!(r.count(), 4);
+    }
+
+    #[test]
+    fn jitter() {
+        let mut d = &b""[..];
+        let mut r = JitterReadCounter::from(&mut d);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        for ms in &[0, 10, 20] {
+            r.update(at(*ms));
+            assert_eq!(r.jitter(), Duration::from_secs(0));
+        }
+
+        // The gaps are 10ms, 10ms, 40ms and 10ms.
+        r.update(at(60));
+        r.update(at(70));
+        assert!((r.mean_gap().as_secs_f64() - 0.0175).abs() < 1e-9);
+        assert!((r.jitter().as_secs_f64() - 168.75f64.sqrt() / 1000.0).abs() < 1e-9);
+    }
+}
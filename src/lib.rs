@@ -20,26 +20,461 @@
 //!
 //! Like the lovable Count von Count from Sesame Street, the `voncount` crate loves to count things.
 //! We provide the `Counter` trait which can be implemented on types which try to count things.
-//! We also provide two structs which implement the `Counter` trait:
+//! We also provide several structs which implement the `Counter` trait:
 //!   * `ReadCounter`
 //!   * `WriteCounter`
+//!   * `Adler32WriteCounter`
+//!   * `Utf8ReadCounter`
+//!   * `Base64ReadCounter` (with the `base64` feature)
+//!   * `FrameReadCounter`
+//!   * `ChunkedReadCounter`
+//!   * `CaptureReadCounter`
+//!   * `RangeCountReadCounter`
+//!   * `IdleReadCounter`
+//!   * `DuplexCounter`
+//!   * `SpanReadCounter`
+//!   * `HeaderSkipReadCounter`
+//!   * `BlockAlignWriteCounter`
+//!   * `StatsReadCounter`
+//!   * `TransformReadCounter`
+//!   * `ItemCounter`
+//!   * `MonotonicCounter`
+//!   * `TimestampReadCounter`
+//!   * `MultiWriteCounter`
+//!   * `BomReadCounter`
+//!   * `AtomicReadCounter`
+//!   * `FooterCheckReadCounter`
+//!   * `CancelableReadCounter`
+//!   * `MinLengthReadCounter`
+//!   * `CountingLines`
+//!   * `DelimitedReadCounter`
+//!   * `AlignReadCounter`
+//!   * `YieldReadCounter`
+//!   * `JitterReadCounter`
+//!   * `RejectReadCounter`
+//!   * `SniffReadCounter`
+//!   * `BitReadCounter`
+//!   * `QuotaWriteCounter`
+//!   * `RollingHashReadCounter`
+//!   * `RunLengthStatsReadCounter`
+//!   * `SemaphoreReadCounter`
+//!   * `RateFloorReadCounter`
+//!   * `ProxyCounter`
+//!   * `SegmentingReadCounter`
+//!   * `SectionedReadCounter`
+//!   * `WindowedChecksumReadCounter`
+//!   * `ChunkedTransferReadCounter`
+//!   * `SliceReadCounter`
+//!   * `FramedWriteCounter`
+//!   * `HexReadCounter`
+//!   * `TaggedReadCounter`
+//!   * `NormalizeReadCounter`
+//!   * `DebouncedCounter`
+//!   * `BufReadCounter`
+//!   * `AutoFlushWriteCounter`
+//!   * `ChaosReadCounter`
+//!   * `ShardedWriteCounter`
+//!   * `DiffWriteCounter`
+//!   * `OffsetWriteCounter`
+//!   * `TokenBucketReadCounter`
+//!   * `MultiFileReadCounter`
+//!   * `OverflowCountReadCounter`
 
+use std::any::Any;
+use std::cmp;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
 use std::io;
+use std::io::{IoSlice, Write};
+use std::mem;
+use std::num::NonZeroUsize;
+use std::ops::{AddAssign, Deref, DerefMut, Range};
+use std::process::{Child, ChildStdin, ChildStdout};
+use std::sync::Arc;
+use std::task::Waker;
+use std::time::{Duration, Instant};
+
+use registry::Registration;
+
+mod adler32;
+mod align;
+mod atomic;
+mod autoflush;
+#[cfg(feature = "base64")]
+mod base64;
+mod bits;
+mod block;
+mod bom;
+mod bufread;
+mod cancel;
+mod capture;
+mod chaos;
+mod chunked;
+mod chunkedtransfer;
+mod copy;
+mod crc32;
+mod debounce;
+mod delimited;
+mod diff;
+mod duplex;
+mod footer;
+mod frame;
+mod framed;
+mod header;
+mod hex;
+mod idle;
+mod item;
+mod jitter;
+mod lines;
+mod minlen;
+mod monotonic;
+mod multi;
+mod multifile;
+mod normalize;
+mod offset;
+mod overflow;
+mod proxy;
+mod quota;
+mod range;
+mod ratefloor;
+mod registry;
+mod reject;
+mod rolling;
+mod runlength;
+mod sectioned;
+mod segment;
+mod semaphore;
+mod sharded;
+mod slice;
+mod snapshot;
+mod sniff;
+mod span;
+mod stats;
+mod tagged;
+mod timestamp;
+mod tokenbucket;
+mod transform;
+mod utf8;
+mod windowed;
+mod yielding;
+
+pub use adler32::Adler32WriteCounter;
+pub use align::AlignReadCounter;
+pub use atomic::AtomicReadCounter;
+pub use autoflush::AutoFlushWriteCounter;
+#[cfg(feature = "base64")]
+pub use base64::Base64ReadCounter;
+pub use bits::BitReadCounter;
+pub use block::BlockAlignWriteCounter;
+pub use bom::{Bom, BomReadCounter};
+pub use bufread::BufReadCounter;
+pub use cancel::CancelableReadCounter;
+pub use capture::CaptureReadCounter;
+pub use chaos::ChaosReadCounter;
+pub use chunked::ChunkedReadCounter;
+pub use chunkedtransfer::ChunkedTransferReadCounter;
+pub use copy::{copy_counted, drain_count, CopyStats};
+pub use debounce::DebouncedCounter;
+pub use delimited::DelimitedReadCounter;
+pub use diff::DiffWriteCounter;
+pub use duplex::DuplexCounter;
+pub use footer::FooterCheckReadCounter;
+pub use frame::FrameReadCounter;
+pub use framed::FramedWriteCounter;
+pub use header::HeaderSkipReadCounter;
+pub use hex::HexReadCounter;
+pub use idle::IdleReadCounter;
+pub use item::ItemCounter;
+pub use jitter::JitterReadCounter;
+pub use lines::CountingLines;
+pub use minlen::MinLengthReadCounter;
+pub use monotonic::MonotonicCounter;
+pub use multi::MultiWriteCounter;
+pub use multifile::MultiFileReadCounter;
+pub use normalize::NormalizeReadCounter;
+pub use offset::OffsetWriteCounter;
+pub use overflow::OverflowCountReadCounter;
+pub use proxy::ProxyCounter;
+pub use quota::{QuotaExceeded, QuotaWriteCounter};
+pub use range::RangeCountReadCounter;
+pub use ratefloor::RateFloorReadCounter;
+pub use registry::Registry;
+pub use reject::RejectReadCounter;
+pub use rolling::RollingHashReadCounter;
+pub use runlength::RunLengthStatsReadCounter;
+pub use sectioned::SectionedReadCounter;
+pub use segment::SegmentingReadCounter;
+pub use semaphore::{Semaphore, SemaphoreReadCounter};
+pub use sharded::{ShardedWriteCounter, Shards};
+pub use slice::SliceReadCounter;
+pub use snapshot::{CountReport, CounterDelta, CounterSnapshot};
+pub use sniff::SniffReadCounter;
+pub use span::{Span, SpanReadCounter};
+pub use stats::{ByteStats, StatsReadCounter};
+pub use tagged::TaggedReadCounter;
+pub use timestamp::TimestampReadCounter;
+pub use tokenbucket::TokenBucketReadCounter;
+pub use transform::TransformReadCounter;
+pub use utf8::Utf8ReadCounter;
+pub use windowed::WindowedChecksumReadCounter;
+pub use yielding::YieldReadCounter;
 
 /// Describes types which count things. What they count is up to them.
 pub trait Counter {
     /// Returns the current count of items counted.
+    #[must_use]
     fn count(&self) -> usize;
+
+    /// Returns the current count if it is nonzero.
+    #[must_use]
+    fn count_nonzero(&self) -> Option<NonZeroUsize> {
+        NonZeroUsize::new(self.count())
+    }
+
+    /// Returns the current count as a percentage of a known `total`.
+    ///
+    /// The result is clamped to at most `100.0`, even if the count exceeds `total`. If `total` is
+    /// zero, there is nothing left to count, so `100.0` is returned.
+    #[must_use]
+    fn percent_of(&self, total: usize) -> f64 {
+        if total == 0 {
+            return 100.0;
+        }
+
+        (self.count() as f64 / total as f64 * 100.0).min(100.0)
+    }
+
+    /// Returns the current count formatted with decimal (SI) prefixes, such as `"1.5 kB"`.
+    ///
+    /// Each prefix is a power of 1000. The value is rounded to at most two decimal places, with
+    /// trailing zeros removed. Counts below 1000 are formatted as whole bytes, such as `"0 B"`.
+    #[must_use]
+    fn count_si(&self) -> String {
+        const UNITS: [&str; 6] = ["kB", "MB", "GB", "TB", "PB", "EB"];
+
+        let count = self.count();
+        if count < 1000 {
+            return format!("{} B", count);
+        }
+
+        let mut value = count as f64 / 1000.0;
+        let mut unit = 0;
+        while value >= 999.995 && unit + 1 < UNITS.len() {
+            value /= 1000.0;
+            unit += 1;
+        }
+
+        let text = format!("{:.2}", value);
+        let text = text.trim_end_matches('0').trim_end_matches('.');
+        format!("{} {}", text, UNITS[unit])
+    }
+}
+
+impl<C: Counter + ?Sized> Counter for &C {
+    /// Returns the count of the referenced counter.
+    fn count(&self) -> usize {
+        (**self).count()
+    }
+}
+
+impl<C: Counter + ?Sized> Counter for &mut C {
+    /// Returns the count of the referenced counter.
+    fn count(&self) -> usize {
+        (**self).count()
+    }
+}
+
+impl<C: Counter + ?Sized> Counter for Box<C> {
+    /// Returns the count of the boxed counter.
+    fn count(&self) -> usize {
+        (**self).count()
+    }
+}
+
+impl<C: Counter> Counter for Option<C> {
+    /// Returns the count of the contained counter, or zero if there is none.
+    ///
+    /// This lets optional instrumentation be queried uniformly: a counter which was never enabled
+    /// has counted nothing.
+    fn count(&self) -> usize {
+        self.as_ref().map_or(0, Counter::count)
+    }
+}
+
+/// Describes counters which transform a stream, counting both sides of the transformation.
+///
+/// The input is what is consumed from the wrapped stream and the output is what is delivered to
+/// the caller. For example, a decoder's input is the encoded bytes and its output is the decoded
+/// bytes. This gives a uniform way to query both sides, regardless of the specific transform.
+pub trait TransformCounter: Counter {
+    /// Returns the number of bytes consumed from the wrapped stream so far.
+    #[must_use]
+    fn input_count(&self) -> usize;
+
+    /// Returns the number of transformed bytes delivered to the caller so far.
+    #[must_use]
+    fn output_count(&self) -> usize;
+
+    /// Returns the ratio of output bytes to input bytes so far.
+    ///
+    /// For a decompressor, this is the compression ratio. If no input has been consumed yet,
+    /// `0.0` is returned.
+    #[must_use]
+    fn ratio(&self) -> f64 {
+        match self.input_count() {
+            0 => 0.0,
+            input => self.output_count() as f64 / input as f64,
+        }
+    }
+}
+
+/// Adds the result of a read operation to a running count.
+///
+/// This is the counting logic of `ReadCounter`, exposed for use in custom implementations, such as
+/// the `poll` functions of a bespoke async runtime. On success, the number of bytes read is added
+/// to `count`. The result is returned unchanged, so errors leave `count` untouched.
+///
+/// # Panics
+///
+/// If the count exceeds `usize::max_value()`.
+pub fn account_read(count: &mut usize, result: io::Result<usize>) -> io::Result<usize> {
+    let size = result?;
+    *count += size;
+    Ok(size)
+}
+
+/// Adds the result of a write operation to a running count.
+///
+/// This is the counterpart of `account_read()` for writes, as used by `WriteCounter`. On success,
+/// the number of bytes written is added to `count`. The result is returned unchanged, so errors
+/// leave `count` untouched.
+///
+/// # Panics
+///
+/// If the count exceeds `usize::max_value()`.
+pub fn account_write(count: &mut usize, result: io::Result<usize>) -> io::Result<usize> {
+    account_read(count, result)
+}
+
+/// Returns the signed difference between the counts of two counters (`a` minus `b`).
+///
+/// This is useful to detect when two counters diverge, such as the bytes read from and written to
+/// either side of a proxy. Since counts range over `usize`, the true difference may not fit in an
+/// `isize`. In this case the result saturates at `isize::max_value()` or `isize::min_value()`.
+pub fn count_delta<A: Counter + ?Sized, B: Counter + ?Sized>(a: &A, b: &B) -> isize {
+    let (a, b) = (a.count(), b.count());
+    let max = isize::MAX as usize;
+
+    if a >= b {
+        if a - b > max {
+            isize::MAX
+        } else {
+            (a - b) as isize
+        }
+    } else if b - a > max {
+        isize::MIN
+    } else {
+        -((b - a) as isize)
+    }
+}
+
+/// Checks that a writer and a reader counted the same number of bytes.
+///
+/// This codifies a common test pattern: data written through a `WriteCounter` and read back
+/// through a `ReadCounter` should round trip without loss.
+///
+/// # Errors
+///
+/// This function will return an error of kind `InvalidData` if the counts differ. The message
+/// includes both counts.
+pub fn assert_round_trip<W: Counter + ?Sized, R: Counter + ?Sized>(
+    writer: &W,
+    reader: &R,
+) -> io::Result<()> {
+    let (written, read) = (writer.count(), reader.count());
+
+    if written != read {
+        let msg = format!(
+            "round trip mismatch: {} bytes written, {} bytes read",
+            written, read
+        );
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+
+    Ok(())
+}
+
+/// Parses a header from a reader, then returns it along with a `ReadCounter` for the body.
+///
+/// The `parse` function reads the header directly from `reader`, so the header bytes are not
+/// counted. The returned counter starts at zero and wraps the reader positioned just after the
+/// header. This separates header parsing from body streaming.
+///
+/// # Errors
+///
+/// This function will return any error returned by `parse`.
+pub fn read_header_then_body<'a, R, H, F>(
+    reader: &'a mut R,
+    parse: F,
+) -> io::Result<(H, ReadCounter<'a, R>)>
+where
+    R: io::Read,
+    F: FnOnce(&mut R) -> io::Result<H>,
+{
+    let header = parse(reader)?;
+    Ok((header, ReadCounter::from(reader)))
 }
 
+/// Receives notifications from counters as they count.
+///
+/// A `CountObserver` can be attached to a `ReadCounter` or `WriteCounter`. It is notified after
+/// each successful operation with the number of bytes counted by that operation (`delta`) and the
+/// new running total (`total`). This allows integrating with any metrics backend without this
+/// crate depending on it. By default, all notifications are ignored.
+///
+/// Observers are attached as `Arc<dyn CountObserver + Send + Sync>`, so that counters with an
+/// observer can still be moved to another thread.
+pub trait CountObserver {
+    /// Called after each successful `read` with the bytes read and the new total.
+    fn on_read(&self, delta: usize, total: usize) {
+        let _ = (delta, total);
+    }
+
+    /// Called after each successful `write` with the bytes written and the new total.
+    fn on_write(&self, delta: usize, total: usize) {
+        let _ = (delta, total);
+    }
+}
+
+// A one-shot action, fired when a count first reaches a threshold.
+type ThresholdAction<'a> = Box<dyn FnOnce(usize) + Send + 'a>;
+
 /// Wraps any implementation of `std::io::Read` and counts the bytes read.
 ///
 /// A `ReadCounter` instance wraps any implementation of `std::io::Read`. Since `ReadCounter` also
 /// implements `std::io::Read` you can use it in place of the other implementation. The
 /// `ReadCounter` will count the number of bytes read.
+///
+/// Adaptors which borrow the `ReadCounter` (such as `counter.by_ref().take(n)`) read through it,
+/// so a sequence of bounded reads accumulates into one running total.
 pub struct ReadCounter<'a, T: 'a + io::Read> {
     reader: &'a mut T,
     count: usize,
+    ops: usize,
+    last: usize,
+    observer: Option<Arc<dyn CountObserver + Send + Sync>>,
+    registration: Option<Registration>,
+    context: Option<Box<dyn Any + Send + Sync>>,
+    threshold: Option<(usize, ThresholdAction<'a>)>,
+    retry: bool,
+    eof_error: bool,
+    errors: HashMap<io::ErrorKind, usize>,
+    expected: Option<(usize, Instant, usize)>,
+    waker: Option<Waker>,
+    lines: Option<usize>,
+    checkpoints: Vec<usize>,
 }
 
 impl<'a, T: 'a + io::Read> From<&'a mut T> for ReadCounter<'a, T> {
@@ -50,121 +485,2008 @@ impl<'a, T: 'a + io::Read> From<&'a mut T> for ReadCounter<'a, T> {
         ReadCounter {
             reader: value,
             count: 0,
+            ops: 0,
+            last: 0,
+            observer: None,
+            registration: None,
+            context: None,
+            threshold: None,
+            retry: false,
+            eof_error: false,
+            errors: HashMap::new(),
+            expected: None,
+            waker: None,
+            lines: None,
+            checkpoints: Vec::new(),
         }
     }
 }
 
-impl<'a, T: 'a + io::Read> Counter for ReadCounter<'a, T> {
-    /// Returns the number of bytes read so far.
-    fn count(&self) -> usize {
-        self.count
+impl<'a, T: 'a + io::Read> ReadCounter<'a, T> {
+    // Records a read of `size` bytes, which have already been added to the count.
+    fn record(&mut self, size: usize) {
+        self.last = self.count - size;
+        self.ops += 1;
+
+        if let Some(ref observer) = self.observer {
+            observer.on_read(size, self.count);
+        }
+
+        if let Some(ref registration) = self.registration {
+            registration.read(size);
+        }
+
+        if let Some(ref waker) = self.waker {
+            if size > 0 {
+                waker.wake_by_ref();
+            }
+        }
+
+        self.check_threshold();
     }
-}
 
-impl<'a, T: 'a + io::Read> io::Read for ReadCounter<'a, T> {
-    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    fn check_threshold(&mut self) {
+        match self.threshold {
+            Some((threshold, _)) if self.count >= threshold => (),
+            _ => return,
+        }
+
+        if let Some((_, action)) = self.threshold.take() {
+            action(self.count);
+        }
+    }
+
+    /// Sets the initial count.
     ///
-    /// # Panics
+    /// This is useful when the wrapped reader is already positioned part way through a stream:
+    /// subsequent counts (and offsets) include the bytes which came before.
+    #[must_use]
+    pub fn with_count(mut self, count: usize) -> ReadCounter<'a, T> {
+        self.count = count;
+        self.last = count;
+        self
+    }
+
+    /// Adds `n` bytes to the count without reading them.
     ///
-    ///   1. When the underlying function panics.
-    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    /// This is useful for accounting for out-of-band bytes, such as protocol framing consumed
+    /// elsewhere. It does not count as an operation or notify any observer or registry. Since
+    /// the added bytes were not part of a read, `offset_range_of_last_read()` is empty afterwards.
+    /// The count saturates at `usize::max_value()` rather than overflowing.
+    pub fn add(&mut self, n: usize) {
+        self.count = self.count.saturating_add(n);
+        self.last = self.count;
+        self.check_threshold();
+    }
+
+    /// Creates a `ReadCounter` which continues counting from a token returned by
+    /// `resume_token()`.
+    ///
+    /// This is useful for interruptible downloads, paired with a range request. The inner reader
+    /// must already be positioned at the offset the token describes: this only seeds the count.
     ///
     /// # Errors
     ///
-    /// This function will error only if the underlying function errors.
-    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
-        let size = self.reader.read(buffer)?;
-        self.count += size;
-        Ok(size)
+    /// This function will return an error of kind `InvalidInput` if `token` does not fit in a
+    /// `usize`, such as a token persisted on a 64-bit platform and resumed on a 32-bit one.
+    pub fn resume(reader: &'a mut T, token: u64) -> io::Result<ReadCounter<'a, T>> {
+        match usize::try_from(token) {
+            Ok(count) => Ok(ReadCounter::from(reader).with_count(count)),
+            Err(_) => {
+                let msg = "resume token does not fit in a usize";
+                Err(io::Error::new(io::ErrorKind::InvalidInput, msg))
+            }
+        }
     }
-}
 
-/// Wraps any implementation of `std::io::Write` and counts the bytes written.
-///
-/// A `WriteCounter` instance wraps any implementation of `std::io::Read`. Since `WriteCounter` also
-/// implements `std::io::Write` you can use it in place of the other implementation. The
-/// `WriteCounter` will count the number of bytes written.
-pub struct WriteCounter<'a, T: 'a + io::Write> {
-    writer: &'a mut T,
-    count: usize,
-}
+    /// Returns a token describing the current count, to be passed to `resume()` later.
+    ///
+    /// The token is just the count, widened to a `u64` so it can be persisted portably.
+    pub fn resume_token(&self) -> u64 {
+        self.count as u64
+    }
 
-impl<'a, T: 'a + io::Write> From<&'a mut T> for WriteCounter<'a, T> {
-    /// Creates a `WriteCounter` by wrapping any implementation of `std::io::Write`.
+    /// Returns the offset within the stream of the next byte to be read.
     ///
-    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
-    fn from(value: &'a mut T) -> WriteCounter<'a, T> {
-        WriteCounter {
-            writer: value,
-            count: 0,
+    /// This is the same as `count()`, including any initial count set by `with_count()`. It is
+    /// useful for reporting the position of errors in parsers.
+    pub fn offset(&self) -> usize {
+        self.count
+    }
+
+    /// Pushes the current offset onto a stack of checkpoints.
+    ///
+    /// Together with `pop_checkpoint()`, this measures the bytes consumed by nested structures,
+    /// such as the nodes of a parse tree.
+    pub fn push_checkpoint(&mut self) {
+        self.checkpoints.push(self.count);
+    }
+
+    /// Pops the most recent checkpoint, returning the number of bytes counted since it was pushed.
+    ///
+    /// This returns `None` if there is no checkpoint to pop.
+    pub fn pop_checkpoint(&mut self) -> Option<usize> {
+        self.checkpoints.pop().map(|offset| self.count - offset)
+    }
+
+    /// Counts the newline (`\n`) bytes read, for `line_count()` and `annotate_error()`.
+    ///
+    /// Line counting examines every byte read, so it is off by default.
+    #[must_use]
+    pub fn with_line_count(mut self) -> ReadCounter<'a, T> {
+        self.lines = Some(0);
+        self
+    }
+
+    /// Returns the number of newline (`\n`) bytes read so far, if enabled by `with_line_count()`.
+    ///
+    /// Bytes added with `add()` or an initial count set by `with_count()` are not examined.
+    pub fn line_count(&self) -> Option<usize> {
+        self.lines
+    }
+
+    /// Annotates an error with the current position in the stream.
+    ///
+    /// If `result` is an error, it is wrapped in an error of the same kind whose message is
+    /// followed by `at byte {offset}`, and by `, line {line}` if line counting is enabled. The
+    /// line number starts at one. The original error is the `source()` of the wrapper returned by
+    /// `get_ref()`, so any payload it carries can still be downcast. Successful results are
+    /// returned unchanged. This centralizes positional context for parser errors.
+    pub fn annotate_error<R>(&self, result: io::Result<R>) -> io::Result<R> {
+        result.map_err(|e| {
+            let kind = e.kind();
+            let annotated = Annotated {
+                error: e,
+                offset: self.count,
+                line: self.lines.map(|lines| lines + 1),
+            };
+
+            io::Error::new(kind, annotated)
+        })
+    }
+
+    /// Returns the number of successful read operations so far.
+    ///
+    /// Each successful call to `read` (including one which reaches the end of the stream) counts
+    /// as one operation, except for calls with an empty buffer. A call to `read_to_end` also
+    /// counts as one operation.
+    pub fn operations(&self) -> usize {
+        self.ops
+    }
+
+    /// Takes a snapshot of the current byte and operation counts.
+    pub fn snapshot(&self) -> CounterSnapshot {
+        CounterSnapshot {
+            bytes: self.count,
+            ops: self.ops,
+            at: Instant::now(),
         }
     }
-}
 
-impl<'a, T: 'a + io::Write> Counter for WriteCounter<'a, T> {
-    /// Returns the number of bytes written so far.
-    fn count(&self) -> usize {
-        self.count
+    /// Borrows this instance as a `std::io::Read` trait object.
+    ///
+    /// This is useful for APIs which take `&mut dyn Read`. Reads through the trait object are
+    /// counted as usual.
+    pub fn as_dyn_read(&mut self) -> &mut dyn io::Read {
+        self
     }
-}
 
-impl<'a, T: 'a + io::Write> io::Write for WriteCounter<'a, T> {
-    /// Proxies to the inner `write` function, counting the bytes written along the way.
+    /// Returns the range of offsets covered by the most recent read.
+    ///
+    /// The range is half-open: `start` is the offset of the first byte read and `end` is the offset
+    /// just past the last byte read. If the most recent read returned no bytes (or if no read has
+    /// happened yet), the range is empty.
+    pub fn offset_range_of_last_read(&self) -> Range<usize> {
+        self.last..self.count
+    }
+
+    /// Attaches an observer which is notified after each successful `read`.
+    ///
+    /// Any previously attached observer is replaced.
+    #[must_use]
+    pub fn with_observer(
+        mut self,
+        observer: Arc<dyn CountObserver + Send + Sync>,
+    ) -> ReadCounter<'a, T> {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Sets a `Waker` which is woken after each `read` which advances the count.
+    ///
+    /// This bridges synchronous counting into async polling: a task which polls the count can
+    /// register its waker here to be woken when there is progress. Any previously set waker is
+    /// replaced. Like observers, the waker is not woken by `add()`.
+    pub fn set_waker(&mut self, waker: Waker) {
+        self.waker = Some(waker);
+    }
+
+    /// Attaches an arbitrary context value, such as a connection ID, to this instance.
+    ///
+    /// Only one context value is stored, so any previously attached value is replaced, even if
+    /// it has a different type. The value must be `Send` and `Sync`, so that the counter can still
+    /// be moved to or shared with another thread.
+    #[must_use]
+    pub fn with_context<C: Any + Send + Sync>(mut self, context: C) -> ReadCounter<'a, T> {
+        self.context = Some(Box::new(context));
+        self
+    }
+
+    /// Returns the attached context value, if there is one of type `C`.
+    pub fn context<C: Any>(&self) -> Option<&C> {
+        self.context.as_ref()?.downcast_ref()
+    }
+
+    /// Retries reads which fail with `ErrorKind::Interrupted`.
+    ///
+    /// In this mode, `read` calls the inner `read` function again after an interruption, until it
+    /// returns data, reaches the end of the stream or fails with a different error. Interrupted
+    /// attempts are not counted as operations.
+    #[must_use]
+    pub fn with_retry_interrupted(mut self) -> ReadCounter<'a, T> {
+        self.retry = true;
+        self
+    }
+
+    /// Reads from the wrapped reader, adding the bytes read to a caller-owned `total`.
+    ///
+    /// This bypasses the state of this instance entirely: its own count, operations, observer
+    /// and other options are left untouched. It is meant for hot loops which already keep a total
+    /// of their own, and is much cheaper than `read` (see `examples/read_into.rs`).
     ///
     /// # Panics
     ///
     ///   1. When the underlying function panics.
-    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///   2. If `total` would exceed `usize::max_value()`.
     ///
     /// # Errors
     ///
-    /// This function will error only if the underlying function errors.
-    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
-        let size = self.writer.write(buffer)?;
-        self.count += size;
+    /// This function will error only if the underlying function errors. In that case, `total` is
+    /// unchanged.
+    pub fn read_into(&mut self, buffer: &mut [u8], total: &mut usize) -> io::Result<usize> {
+        let size = self.reader.read(buffer)?;
+        *total += size;
         Ok(size)
     }
 
-    /// Proxies to the inner `flush` function.
-    fn flush(&mut self) -> Result<(), io::Error> {
-        self.writer.flush()
+    /// Reads exactly `n` bytes into a newly allocated `Vec`.
+    ///
+    /// This is a convenience for record-based parsing. The bytes are read with `read_exact`, so
+    /// they are counted just like any other read.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors (other than with the kind
+    /// `Interrupted`, which is retried). It will also return an error of kind `UnexpectedEof` if
+    /// the stream ends first. Bytes read before the error are still counted, but are discarded.
+    pub fn read_exact_vec(&mut self, n: usize) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0u8; n];
+        io::Read::read_exact(self, &mut buffer)?;
+        Ok(buffer)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::{Read, Write};
+    /// Treats the end of the stream as an error.
+    ///
+    /// In this mode, when the inner `read` function returns `Ok(0)` for a non-empty buffer, `read`
+    /// returns an error of kind `UnexpectedEof` instead. This lets strict protocol parsers
+    /// propagate truncation with `?`. It does not affect `read_to_end`, which reads until the end
+    /// of the stream by design.
+    #[must_use]
+    pub fn with_error_on_eof(mut self) -> ReadCounter<'a, T> {
+        self.eof_error = true;
+        self
+    }
 
-    const DATA: &'static [u8] = &[1u8, 2u8, 3u8];
+    /// Consumes this instance, returning a report of its final metrics.
+    ///
+    /// This releases the borrow of the wrapped reader.
+    pub fn finalize(self) -> CountReport {
+        CountReport {
+            bytes: self.count,
+            ops: self.ops,
+            lines: self.lines,
+            pending: None,
+            error_kinds: self.errors,
+        }
+    }
 
-    #[test]
-    fn read() {
-        let mut d = DATA;
-        let mut r = ReadCounter::from(&mut d);
+    /// Sets the expected total size of the stream, enabling `eta()`.
+    ///
+    /// The rate used to estimate the time remaining is measured from this call onwards.
+    #[must_use]
+    pub fn with_expected_total(mut self, total: usize) -> ReadCounter<'a, T> {
+        self.expected = Some((total, Instant::now(), self.count));
+        self
+    }
 
-        for (i, v) in DATA.iter().enumerate() {
-            let mut b = [0u8];
+    /// Returns the estimated time remaining until the expected total has been read.
+    ///
+    /// The estimate is based on the average rate since `with_expected_total()` was called. This
+    /// returns `None` if no expected total was set or no bytes have been read since, since there
+    /// is no rate to estimate from yet.
+    pub fn eta(&self) -> Option<Duration> {
+        let (total, start, initial) = self.expected?;
+        let read = self.count.saturating_sub(initial);
+        let secs = start.elapsed().as_secs_f64();
 
-            assert_eq!(r.read(&mut b).unwrap(), 1);
-            assert_eq!(r.count(), i + 1);
-            assert_eq!(b[0], *v);
+        if read == 0 || secs == 0.0 {
+            return None;
         }
+
+        let remaining = total.saturating_sub(self.count);
+        Some(Duration::from_secs_f64(
+            remaining as f64 * secs / read as f64,
+        ))
     }
 
-    #[test]
-    fn write() {
-        let mut b: Vec<u8> = Vec::new();
+    /// Returns the number of errors returned by the wrapped reader, broken down by kind.
+    ///
+    /// This includes errors which were retried (see `with_retry_interrupted()`) as well as those
+    /// which were passed on to the caller.
+    pub fn error_kinds(&self) -> &HashMap<io::ErrorKind, usize> {
+        &self.errors
+    }
 
-        {
-            let mut w = WriteCounter::from(&mut b);
+    /// Attaches an action which fires once, when the count first reaches `threshold`.
+    ///
+    /// The action is called with the count at that moment, which may be well past `threshold` if
+    /// a single read crossed it. It is never called again. Any previously attached action which
+    /// has not yet fired is replaced. The action must be `Send`, so that the counter remains
+    /// `Send`.
+    #[must_use]
+    pub fn on_threshold<F: FnOnce(usize) + Send + 'a>(
+        mut self,
+        threshold: usize,
+        action: F,
+    ) -> ReadCounter<'a, T> {
+        self.threshold = Some((threshold, Box::new(action)));
+        self
+    }
 
-            for (i, v) in DATA.iter().enumerate() {
-                assert_eq!(w.write(&[*v]).unwrap(), 1);
-                assert_eq!(w.count(), i + 1);
-            }
+    /// Registers this instance with a `Registry`, which aggregates the bytes counted from now on.
+    ///
+    /// Any previous registration is replaced. The registration ends when this instance is dropped.
+    pub fn register(&mut self, registry: &Arc<Registry>) {
+        self.registration = Some(Registration::new(registry));
+    }
+
+    /// Replaces the wrapped reader, returning the previously wrapped one.
+    ///
+    /// The accumulated count is preserved, so bytes read from the new reader are added to the
+    /// running total. This is useful for cumulative accounting across reconnections.
+    ///
+    /// The new reader must be borrowed for the same lifetime `'a` as the original one. Therefore,
+    /// it must outlive this instance, just as the original reader did.
+    pub fn replace_inner(&mut self, new: &'a mut T) -> &'a mut T {
+        mem::replace(&mut self.reader, new)
+    }
+}
+
+impl<'a> ReadCounter<'a, ChildStdout> {
+    /// Creates a `ReadCounter` which counts the bytes read from the standard output of a child
+    /// process.
+    ///
+    /// The handle is borrowed from `child`, so it stays available (for example, to be dropped
+    /// before calling `wait()`).
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error of kind `InvalidInput` if the standard output of the
+    /// child was not piped.
+    pub fn from_child_stdout(child: &'a mut Child) -> io::Result<ReadCounter<'a, ChildStdout>> {
+        match child.stdout.as_mut() {
+            Some(stdout) => Ok(ReadCounter::from(stdout)),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "child process stdout was not piped",
+            )),
         }
+    }
+}
 
-        assert_eq!(&b[..], DATA);
+impl<'a, T: 'a + io::Read + io::Seek> ReadCounter<'a, T> {
+    /// Returns the number of bytes remaining in the wrapped reader.
+    ///
+    /// This seeks to the end of the stream to discover its length and then restores the current
+    /// position. It is useful for progress reporting without a caller-supplied total.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if any of the underlying seeks error, such as when the inner
+    /// reader is not actually seekable (e.g. a pipe).
+    pub fn remaining(&mut self) -> io::Result<u64> {
+        let position = self.reader.stream_position()?;
+        let length = self.reader.seek(io::SeekFrom::End(0))?;
+        self.reader.seek(io::SeekFrom::Start(position))?;
+        Ok(length.saturating_sub(position))
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for ReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for ReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// If `buffer` is empty, this returns `Ok(0)` immediately without calling the inner `read`
+    /// function. Such a call is not counted as an operation.
+    ///
+    /// If `with_retry_interrupted()` was used, errors of kind `Interrupted` are retried.
+    /// If `with_error_on_eof()` was used, reaching the end of the stream is an error of kind
+    /// `UnexpectedEof`.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors. If `with_retry_interrupted()`
+    /// was used, errors of kind `Interrupted` are retried rather than returned. If
+    /// `with_error_on_eof()` was used, it will also return an error of kind `UnexpectedEof` when
+    /// the inner `read` function reaches the end of the stream.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        let size = loop {
+            match account_read(&mut self.count, self.reader.read(buffer)) {
+                Ok(size) => break size,
+                Err(e) => {
+                    *self.errors.entry(e.kind()).or_insert(0) += 1;
+                    if !self.retry || e.kind() != io::ErrorKind::Interrupted {
+                        return Err(e);
+                    }
+                }
+            }
+        };
+
+        if size == 0 && self.eof_error {
+            let msg = "stream ended unexpectedly";
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, msg));
+        }
+
+        if let Some(ref mut lines) = self.lines {
+            *lines += newlines(&buffer[..size]);
+        }
+
+        self.record(size);
+        Ok(size)
+    }
+
+    /// Proxies to the inner `read_to_end` function, counting the bytes read along the way.
+    ///
+    /// This preserves any optimizations of the inner implementation, such as pre-allocating the
+    /// buffer using the known size of a file. Bytes appended to `buffer` are counted even if an
+    /// error occurs part way through.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read_to_end(&mut self, buffer: &mut Vec<u8>) -> Result<usize, io::Error> {
+        let start = buffer.len();
+        let result = self.reader.read_to_end(buffer);
+        if let Some(ref mut lines) = self.lines {
+            *lines += newlines(&buffer[start..]);
+        }
+
+        self.count += buffer.len() - start;
+        self.record(buffer.len() - start);
+
+        if let Err(ref e) = result {
+            *self.errors.entry(e.kind()).or_insert(0) += 1;
+        }
+
+        result
+    }
+}
+
+// Counts the newline bytes in a buffer.
+fn newlines(buffer: &[u8]) -> usize {
+    buffer.iter().filter(|b| **b == b'\n').count()
+}
+
+// An error annotated with a position by `ReadCounter::annotate_error()`.
+#[derive(Debug)]
+struct Annotated {
+    error: io::Error,
+    offset: usize,
+    line: Option<usize>,
+}
+
+impl fmt::Display for Annotated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at byte {}", self.error, self.offset)?;
+        match self.line {
+            Some(line) => write!(f, ", line {}", line),
+            None => Ok(()),
+        }
+    }
+}
+
+impl error::Error for Annotated {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<'a, T: 'a + io::Read> AddAssign<usize> for ReadCounter<'a, T> {
+    /// Adds `n` bytes to the count, just like `ReadCounter::add()`.
+    fn add_assign(&mut self, n: usize) {
+        self.add(n);
+    }
+}
+
+// Either a borrowed or an owned instance of the wrapped type.
+enum Handle<'a, T: 'a> {
+    Borrowed(&'a mut T),
+    Owned(T),
+}
+
+impl<'a, T: 'a> Deref for Handle<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match *self {
+            Handle::Borrowed(ref value) => value,
+            Handle::Owned(ref value) => value,
+        }
+    }
+}
+
+impl<'a, T: 'a> DerefMut for Handle<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        match *self {
+            Handle::Borrowed(ref mut value) => value,
+            Handle::Owned(ref mut value) => value,
+        }
+    }
+}
+
+/// Wraps any implementation of `std::io::Write` and counts the bytes written.
+///
+/// A `WriteCounter` instance wraps any implementation of `std::io::Read`. Since `WriteCounter` also
+/// implements `std::io::Write` you can use it in place of the other implementation. The
+/// `WriteCounter` will count the number of bytes written.
+///
+/// Formatted output (such as from `write!`) is written through `write`, so it is counted too.
+pub struct WriteCounter<'a, T: 'a + io::Write> {
+    writer: Handle<'a, T>,
+    count: usize,
+    ops: usize,
+    pending: usize,
+    lines: Option<usize>,
+    flushes: usize,
+    redundant_flushes: usize,
+    flushed_at: Option<usize>,
+    observer: Option<Arc<dyn CountObserver + Send + Sync>>,
+    registration: Option<Registration>,
+    context: Option<Box<dyn Any + Send + Sync>>,
+}
+
+impl<'a, T: 'a + io::Write> From<&'a mut T> for WriteCounter<'a, T> {
+    /// Creates a `WriteCounter` by wrapping any implementation of `std::io::Write`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> WriteCounter<'a, T> {
+        WriteCounter {
+            writer: Handle::Borrowed(value),
+            count: 0,
+            ops: 0,
+            pending: 0,
+            lines: None,
+            flushes: 0,
+            redundant_flushes: 0,
+            flushed_at: None,
+            observer: None,
+            registration: None,
+            context: None,
+        }
+    }
+}
+
+impl WriteCounter<'static, io::Stdout> {
+    /// Creates a `WriteCounter` which counts the bytes written to the standard output.
+    ///
+    /// The returned instance owns an `io::Stdout` handle rather than an `io::StdoutLock`, since an
+    /// owned `'static` lock needs a newer Rust than the 1.53 this crate supports. So each write
+    /// takes the lock just for its duration, and writes from other threads (such as with
+    /// `println!`) may be interleaved with the writes counted here; they are not counted.
+    #[must_use]
+    pub fn stdout() -> WriteCounter<'static, io::Stdout> {
+        WriteCounter::owned(io::stdout())
+    }
+}
+
+impl WriteCounter<'static, io::Stderr> {
+    /// Creates a `WriteCounter` which counts the bytes written to the standard error.
+    ///
+    /// The returned instance owns an `io::Stderr` handle rather than an `io::StderrLock`, since an
+    /// owned `'static` lock needs a newer Rust than the 1.53 this crate supports. So each write
+    /// takes the lock just for its duration, and writes from other threads (such as with
+    /// `eprintln!`) may be interleaved with the writes counted here; they are not counted.
+    #[must_use]
+    pub fn stderr() -> WriteCounter<'static, io::Stderr> {
+        WriteCounter::owned(io::stderr())
+    }
+}
+
+impl WriteCounter<'static, ChildStdin> {
+    /// Creates a `WriteCounter` which counts the bytes written to the standard input of a child
+    /// process.
+    ///
+    /// The handle is taken from `child`, so dropping the returned instance closes the pipe. This
+    /// signals the end of the input to the child.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error of kind `InvalidInput` if the standard input of the
+    /// child was not piped (or has already been taken).
+    pub fn from_child_stdin(child: &mut Child) -> io::Result<WriteCounter<'static, ChildStdin>> {
+        match child.stdin.take() {
+            Some(stdin) => Ok(WriteCounter::owned(stdin)),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "child process stdin was not piped",
+            )),
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Write> WriteCounter<'a, T> {
+    /// Creates a `WriteCounter` which takes ownership of any implementation of `std::io::Write`.
+    ///
+    /// This is useful when the writer cannot be borrowed for long enough, such as a lock which
+    /// must be kept alongside the counter. The writer is dropped along with this instance.
+    #[must_use]
+    pub fn owned(writer: T) -> WriteCounter<'a, T> {
+        WriteCounter {
+            writer: Handle::Owned(writer),
+            count: 0,
+            ops: 0,
+            pending: 0,
+            lines: None,
+            flushes: 0,
+            redundant_flushes: 0,
+            flushed_at: None,
+            observer: None,
+            registration: None,
+            context: None,
+        }
+    }
+
+    // Records a write of `size` bytes, which have already been added to the count.
+    fn record(&mut self, size: usize) {
+        self.ops += 1;
+        self.pending += size;
+
+        if let Some(ref observer) = self.observer {
+            observer.on_write(size, self.count);
+        }
+
+        if let Some(ref registration) = self.registration {
+            registration.written(size);
+        }
+    }
+
+    /// Attaches an observer which is notified after each successful `write`.
+    ///
+    /// Any previously attached observer is replaced.
+    #[must_use]
+    pub fn with_observer(
+        mut self,
+        observer: Arc<dyn CountObserver + Send + Sync>,
+    ) -> WriteCounter<'a, T> {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Attaches an arbitrary context value, such as a connection ID, to this instance.
+    ///
+    /// Only one context value is stored, so any previously attached value is replaced, even if
+    /// it has a different type. The value must be `Send` and `Sync`, so that the counter can still
+    /// be moved to or shared with another thread.
+    #[must_use]
+    pub fn with_context<C: Any + Send + Sync>(mut self, context: C) -> WriteCounter<'a, T> {
+        self.context = Some(Box::new(context));
+        self
+    }
+
+    /// Returns the attached context value, if there is one of type `C`.
+    pub fn context<C: Any>(&self) -> Option<&C> {
+        self.context.as_ref()?.downcast_ref()
+    }
+
+    /// Registers this instance with a `Registry`, which aggregates the bytes counted from now on.
+    ///
+    /// Any previous registration is replaced. The registration ends when this instance is dropped.
+    pub fn register(&mut self, registry: &Arc<Registry>) {
+        self.registration = Some(Registration::new(registry));
+    }
+
+    /// Returns the number of successful write operations so far.
+    ///
+    /// Each successful call to `write` or `write_vectored` counts as one operation.
+    pub fn operations(&self) -> usize {
+        self.ops
+    }
+
+    /// Consumes this instance, returning a report of its final metrics.
+    ///
+    /// This releases the borrow of the wrapped writer (or drops it, if it is owned). The writer is
+    /// not flushed, so `pending` reports any bytes at risk.
+    pub fn finalize(self) -> CountReport {
+        CountReport {
+            bytes: self.count,
+            ops: self.ops,
+            lines: self.lines,
+            pending: Some(self.pending),
+            error_kinds: HashMap::new(),
+        }
+    }
+
+    /// Adds `n` bytes to the count without writing them.
+    ///
+    /// This is useful for accounting for out-of-band bytes, such as protocol framing written
+    /// elsewhere. It does not count as an operation, change `pending()` or notify any observer
+    /// or registry. The count saturates at `usize::max_value()` rather than overflowing.
+    pub fn add(&mut self, n: usize) {
+        self.count = self.count.saturating_add(n);
+    }
+
+    /// Returns the number of bytes written since the last successful flush.
+    ///
+    /// If a flush fails, this is left unchanged. So it describes how much data is at risk.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Counts the newline (`\n`) bytes written, for `line_count()`.
+    ///
+    /// Line counting examines every byte written, so it is off by default.
+    #[must_use]
+    pub fn with_line_count(mut self) -> WriteCounter<'a, T> {
+        self.lines = Some(0);
+        self
+    }
+
+    /// Returns the number of newline (`\n`) bytes written so far, if enabled by
+    /// `with_line_count()`.
+    ///
+    /// Only bytes accepted by the inner writer are examined. So after a short write, newlines in
+    /// the rejected remainder of the buffer are not counted.
+    pub fn line_count(&self) -> Option<usize> {
+        self.lines
+    }
+
+    /// Returns the number of times `flush` has been called, whether or not it succeeded.
+    pub fn flushes(&self) -> usize {
+        self.flushes
+    }
+
+    /// Returns the number of flushes which were redundant.
+    ///
+    /// A flush is redundant when the count has not changed since the previous flush, so there
+    /// was nothing new to flush. The first flush is never redundant. This is useful for finding
+    /// wasteful flush patterns.
+    pub fn redundant_flushes(&self) -> usize {
+        self.redundant_flushes
+    }
+
+    /// Takes a snapshot of the current byte and operation counts.
+    pub fn snapshot(&self) -> CounterSnapshot {
+        CounterSnapshot {
+            bytes: self.count,
+            ops: self.ops,
+            at: Instant::now(),
+        }
+    }
+
+    /// Borrows this instance as a `std::io::Write` trait object.
+    ///
+    /// This is useful for APIs which take `&mut dyn Write`. Writes through the trait object are
+    /// counted as usual.
+    pub fn as_dyn_write(&mut self) -> &mut dyn io::Write {
+        self
+    }
+
+    /// Writes all of the buffers using vectored writes, counting every byte written.
+    ///
+    /// This is like `write_all`, but for a sequence of buffers. It calls `write_vectored`
+    /// repeatedly until every buffer has been written, keeping track of how far it has advanced
+    /// through the buffers internally. If a buffer is only partially written, its remainder is
+    /// written with `write_all` before continuing with the following buffers.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors (other than with the kind
+    /// `Interrupted`, which is retried) or returns that it wrote zero bytes. Bytes written before
+    /// the error are still counted.
+    pub fn write_all_vectored_counted(&mut self, buffers: &[IoSlice]) -> Result<(), io::Error> {
+        let mut start = 0;
+
+        loop {
+            while start < buffers.len() && buffers[start].is_empty() {
+                start += 1;
+            }
+
+            if start == buffers.len() {
+                return Ok(());
+            }
+
+            let mut size = match self.write_vectored(&buffers[start..]) {
+                Ok(0) => {
+                    let msg = "failed to write whole buffer";
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, msg));
+                }
+                Ok(size) => size,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            while size >= buffers[start].len() {
+                size -= buffers[start].len();
+                start += 1;
+
+                if start == buffers.len() {
+                    return Ok(());
+                }
+            }
+
+            if size > 0 {
+                self.write_all(&buffers[start][size..])?;
+                start += 1;
+            }
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Write> Counter for WriteCounter<'a, T> {
+    /// Returns the number of bytes written so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Write> io::Write for WriteCounter<'a, T> {
+    /// Proxies to the inner `write` function, counting the bytes written along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        let size = account_write(&mut self.count, self.writer.write(buffer))?;
+        if let Some(ref mut lines) = self.lines {
+            *lines += newlines(&buffer[..size]);
+        }
+
+        self.record(size);
+        Ok(size)
+    }
+
+    /// Proxies to the inner `write_vectored` function, counting the bytes written along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn write_vectored(&mut self, buffers: &[IoSlice]) -> Result<usize, io::Error> {
+        let size = account_write(&mut self.count, self.writer.write_vectored(buffers))?;
+
+        if let Some(ref mut lines) = self.lines {
+            let mut left = size;
+            for buffer in buffers {
+                let n = cmp::min(left, buffer.len());
+                *lines += newlines(&buffer[..n]);
+                left -= n;
+            }
+        }
+
+        self.record(size);
+        Ok(size)
+    }
+
+    /// Proxies to the inner `flush` function.
+    ///
+    /// On success, the count of bytes pending a flush is reset to zero. Every call is tallied by
+    /// `flushes()` and, if nothing was written since the previous call, `redundant_flushes()`.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.flushes += 1;
+        if self.flushed_at == Some(self.count) {
+            self.redundant_flushes += 1;
+        }
+        self.flushed_at = Some(self.count);
+
+        self.writer.flush()?;
+        self.pending = 0;
+        Ok(())
+    }
+}
+
+impl<'a, T: 'a + io::Write> AddAssign<usize> for WriteCounter<'a, T> {
+    /// Adds `n` bytes to the count, just like `WriteCounter::add()`.
+    fn add_assign(&mut self, n: usize) {
+        self.add(n);
+    }
+}
+
+#[cfg(test)]
+mod testutil {
+    use std::cmp;
+    use std::io;
+    use std::io::{Read, Write};
+
+    use Counter;
+
+    // A reader which returns at most a fixed number of bytes per call.
+    pub struct Chunked<'a>(pub &'a [u8], pub usize);
+
+    impl<'a> Read for Chunked<'a> {
+        fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+            let size = cmp::min(self.1, buffer.len());
+            self.0.read(&mut buffer[..size])
+        }
+    }
+
+    // A counter with a count set by the test.
+    pub struct Fixed(pub usize);
+
+    impl Counter for Fixed {
+        fn count(&self) -> usize {
+            self.0
+        }
+    }
+
+    // A stream which reads from one buffer, at most a fixed number of bytes per call, and writes
+    // to another.
+    pub struct Duplex<'a> {
+        pub input: Chunked<'a>,
+        pub output: Vec<u8>,
+    }
+
+    impl<'a> Duplex<'a> {
+        pub fn new(input: &'a [u8], chunk: usize) -> Duplex<'a> {
+            Duplex {
+                input: Chunked(input, chunk),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl<'a> Read for Duplex<'a> {
+        fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+            self.input.read(buffer)
+        }
+    }
+
+    impl<'a> Write for Duplex<'a> {
+        fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+            self.output.write(buffer)
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    // A writer which accepts at most a fixed number of bytes per call.
+    pub struct Short(pub Vec<u8>, pub usize);
+
+    impl Write for Short {
+        fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+            let size = cmp::min(self.1, buffer.len());
+            self.0.extend_from_slice(&buffer[..size]);
+            Ok(size)
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::sync::Mutex;
+    use testutil::Fixed;
+
+    const DATA: &[u8] = &[1u8, 2u8, 3u8];
+
+    #[test]
+    fn read() {
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d);
+
+        for (i, v) in DATA.iter().enumerate() {
+            let mut b = [0u8];
+
+            assert_eq!(r.read(&mut b).unwrap(), 1);
+            assert_eq!(r.count(), i + 1);
+            assert_eq!(b[0], *v);
+        }
+    }
+
+    #[test]
+    fn replace_inner() {
+        let mut a = DATA;
+        let mut b = DATA;
+        let mut r = ReadCounter::from(&mut a);
+        let mut buf = [0u8; 3];
+
+        assert_eq!(r.read(&mut buf[..2]).unwrap(), 2);
+        assert_eq!(r.count(), 2);
+
+        let old = r.replace_inner(&mut b);
+        assert_eq!(*old, &DATA[2..]);
+
+        assert_eq!(r.read(&mut buf).unwrap(), 3);
+        assert_eq!(r.count(), 5);
+        assert_eq!(&buf, DATA);
+    }
+
+    #[test]
+    fn write() {
+        let mut b: Vec<u8> = Vec::new();
+
+        {
+            let mut w = WriteCounter::from(&mut b);
+
+            for (i, v) in DATA.iter().enumerate() {
+                assert_eq!(w.write(&[*v]).unwrap(), 1);
+                assert_eq!(w.count(), i + 1);
+            }
+        }
+
+        assert_eq!(&b[..], DATA);
+    }
+
+    #[test]
+    fn operations() {
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d);
+        let mut b = [0u8; 2];
+        while r.read(&mut b).unwrap() > 0 {}
+        assert_eq!(r.operations(), 3);
+
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = WriteCounter::from(&mut v);
+        w.write_all(DATA).unwrap();
+        w.write_all(DATA).unwrap();
+        assert_eq!(w.operations(), 2);
+    }
+
+    #[test]
+    fn empty_read() {
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d);
+        let mut b = [0u8; 2];
+        r.read_exact(&mut b).unwrap();
+
+        assert_eq!(r.read(&mut []).unwrap(), 0);
+        assert_eq!(r.count(), 2);
+        assert_eq!(r.operations(), 1);
+    }
+
+    #[derive(Default)]
+    struct Mock {
+        reads: Mutex<Vec<(usize, usize)>>,
+        writes: Mutex<Vec<(usize, usize)>>,
+    }
+
+    impl CountObserver for Mock {
+        fn on_read(&self, delta: usize, total: usize) {
+            self.reads.lock().unwrap().push((delta, total));
+        }
+
+        fn on_write(&self, delta: usize, total: usize) {
+            self.writes.lock().unwrap().push((delta, total));
+        }
+    }
+
+    #[test]
+    fn observer() {
+        let mock = Arc::new(Mock::default());
+
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d).with_observer(mock.clone());
+        let mut b = [0u8; 2];
+        assert_eq!(r.read(&mut b).unwrap(), 2);
+        assert_eq!(r.read(&mut b).unwrap(), 1);
+        assert_eq!(*mock.reads.lock().unwrap(), vec![(2, 2), (1, 3)]);
+
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = WriteCounter::from(&mut v).with_observer(mock.clone());
+        assert_eq!(w.write(DATA).unwrap(), 3);
+        assert_eq!(*mock.writes.lock().unwrap(), vec![(3, 3)]);
+    }
+
+    struct Hinted<'a>(&'a [u8], bool);
+
+    impl<'a> Read for Hinted<'a> {
+        fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+            self.0.read(buffer)
+        }
+
+        fn read_to_end(&mut self, buffer: &mut Vec<u8>) -> Result<usize, io::Error> {
+            self.1 = true;
+            buffer.reserve_exact(self.0.len());
+            self.0.read_to_end(buffer)
+        }
+    }
+
+    #[test]
+    fn read_to_end() {
+        let data = vec![7u8; 1 << 16];
+        let mut h = Hinted(&data, false);
+
+        {
+            let mut r = ReadCounter::from(&mut h);
+            let mut v = vec![1u8];
+            assert_eq!(r.read_to_end(&mut v).unwrap(), data.len());
+            assert_eq!(r.count(), data.len());
+            assert_eq!(v.len(), data.len() + 1);
+        }
+
+        assert!(h.1);
+    }
+
+    #[test]
+    fn count_delta() {
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d);
+        let mut b = [0u8; 2];
+        assert_eq!(r.read(&mut b).unwrap(), 2);
+
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = WriteCounter::from(&mut v);
+        assert_eq!(w.write(DATA).unwrap(), 3);
+
+        assert_eq!(super::count_delta(&r, &w), -1);
+        assert_eq!(super::count_delta(&w, &r), 1);
+        assert_eq!(super::count_delta(&r, &r), 0);
+
+        let (big, zero) = (Fixed(usize::MAX), Fixed(0));
+        assert_eq!(super::count_delta(&big, &zero), isize::MAX);
+        assert_eq!(super::count_delta(&zero, &big), isize::MIN);
+    }
+
+    #[test]
+    fn offsets() {
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d).with_count(10);
+        let mut b = [0u8; 2];
+        assert_eq!(r.offset(), 10);
+        assert_eq!(r.offset_range_of_last_read(), 10..10);
+
+        assert_eq!(r.read(&mut b).unwrap(), 2);
+        assert_eq!(r.offset_range_of_last_read(), 10..12);
+
+        assert_eq!(r.read(&mut b).unwrap(), 1);
+        assert_eq!(r.offset_range_of_last_read(), 12..13);
+
+        assert_eq!(r.read(&mut b).unwrap(), 0);
+        assert_eq!(r.offset_range_of_last_read(), 13..13);
+        assert_eq!(r.offset(), 13);
+        assert_eq!(r.count(), 13);
+    }
+
+    struct Vectored(Vec<u8>);
+
+    impl Write for Vectored {
+        fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+            let size = buffer.len().min(2);
+            self.0.extend_from_slice(&buffer[..size]);
+            Ok(size)
+        }
+
+        fn write_vectored(&mut self, buffers: &[IoSlice]) -> Result<usize, io::Error> {
+            let mut size = 0;
+
+            for buffer in buffers.iter().take(2) {
+                let n = buffer.len().min(5 - size);
+                self.0.extend_from_slice(&buffer[..n]);
+                size += n;
+            }
+
+            Ok(size)
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_all_vectored_counted() {
+        let slices: [&[u8]; 5] = [b"abc", b"", b"defgh", b"ij", b"klmnopq"];
+        let bufs: Vec<IoSlice> = slices.iter().map(|s| IoSlice::new(s)).collect();
+        let mut v = Vectored(Vec::new());
+
+        {
+            let mut w = WriteCounter::from(&mut v);
+            w.write_all_vectored_counted(&bufs).unwrap();
+            assert_eq!(w.count(), slices.iter().map(|s| s.len()).sum());
+        }
+
+        assert_eq!(&v.0[..], b"abcdefghijklmnopq");
+    }
+
+    #[test]
+    fn percent_of() {
+        assert_eq!(Fixed(0).percent_of(4), 0.0);
+        assert_eq!(Fixed(1).percent_of(4), 25.0);
+        assert_eq!(Fixed(4).percent_of(4), 100.0);
+        assert_eq!(Fixed(8).percent_of(4), 100.0);
+        assert_eq!(Fixed(0).percent_of(0), 100.0);
+    }
+
+    #[test]
+    fn by_ref_take() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut d = &data[..];
+        let mut r = ReadCounter::from(&mut d);
+
+        let mut first = Vec::new();
+        r.by_ref().take(4).read_to_end(&mut first).unwrap();
+        assert_eq!(&first[..], &data[..4]);
+        assert_eq!(r.count(), 4);
+
+        let mut second = Vec::new();
+        r.by_ref().take(3).read_to_end(&mut second).unwrap();
+        assert_eq!(&second[..], &data[4..7]);
+        assert_eq!(r.count(), 7);
+    }
+
+    #[test]
+    fn remaining() {
+        let mut c = io::Cursor::new(vec![0u8; 10]);
+        let mut r = ReadCounter::from(&mut c);
+        let mut buf = [0u8; 4];
+
+        assert_eq!(r.remaining().unwrap(), 10);
+        r.read_exact(&mut buf).unwrap();
+        assert_eq!(r.remaining().unwrap(), 6);
+        r.read_exact(&mut buf).unwrap();
+        assert_eq!(r.remaining().unwrap(), 2);
+        assert_eq!(r.count(), 8);
+    }
+
+    // A writer which appends to a buffer shared by all of its clones.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buffer)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn owned() {
+        let mut w = WriteCounter::owned(Vec::new());
+        w.write_all(DATA).unwrap();
+        assert_eq!(w.count(), DATA.len());
+
+        // A substitute for the standard output: an owned handle to a shared buffer.
+        let captured = Captured::default();
+        let mut w = WriteCounter::owned(captured.clone());
+        writeln!(w, "hello").unwrap();
+        w.write_all(DATA).unwrap();
+        w.flush().unwrap();
+        assert_eq!(w.count(), 6 + DATA.len());
+        drop(w);
+        assert_eq!(captured.0.lock().unwrap().len(), 6 + DATA.len());
+
+        let mut w = WriteCounter::stdout();
+        w.write_all(b"").unwrap();
+        w.flush().unwrap();
+        assert_eq!(w.count(), 0);
+
+        let mut w = WriteCounter::stderr();
+        w.write_all(b"").unwrap();
+        assert_eq!(w.count(), 0);
+    }
+
+    #[test]
+    fn snapshot() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut d = &data[..];
+        let mut r = ReadCounter::from(&mut d);
+        let mut b = [0u8; 3];
+
+        assert_eq!(r.read(&mut b).unwrap(), 3);
+        let first = r.snapshot();
+
+        assert_eq!(r.read(&mut b).unwrap(), 3);
+        assert_eq!(r.read(&mut b).unwrap(), 3);
+        let second = r.snapshot();
+
+        let delta = second.delta(&first);
+        assert_eq!(delta.bytes, 6);
+        assert_eq!(delta.ops, 2);
+        assert!(second.at >= first.at);
+        assert_eq!(first.delta(&second).bytes, 0);
+
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = WriteCounter::from(&mut v);
+        let before = w.snapshot();
+        w.write_all(DATA).unwrap();
+        assert_eq!(w.snapshot().delta(&before).bytes, DATA.len());
+    }
+
+    #[test]
+    fn write_fmt() {
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = WriteCounter::from(&mut v);
+        let value = 3.25;
+
+        write!(w, "{} \u{e9}t\u{e9} {:>6}", value, 42).unwrap();
+        let rendered = format!("{} \u{e9}t\u{e9} {:>6}", value, 42);
+        assert_eq!(w.count(), rendered.len());
+    }
+
+    #[test]
+    fn registry() {
+        let registry = Registry::new();
+        let mut a = DATA;
+        let mut b = DATA;
+        let mut v: Vec<u8> = Vec::new();
+        let mut buf = [0u8; 8];
+
+        {
+            let mut r1 = ReadCounter::from(&mut a);
+            let mut r2 = ReadCounter::from(&mut b);
+            let mut w = WriteCounter::from(&mut v);
+
+            assert_eq!(r1.read(&mut buf[..1]).unwrap(), 1);
+            r1.register(&registry);
+            r2.register(&registry);
+            w.register(&registry);
+            assert_eq!(registry.registered(), 3);
+
+            assert_eq!(r1.read(&mut buf).unwrap(), 2);
+            assert_eq!(r2.read(&mut buf).unwrap(), 3);
+            w.write_all(DATA).unwrap();
+        }
+
+        assert_eq!(registry.registered(), 0);
+        assert_eq!(registry.bytes_read(), 5);
+        assert_eq!(registry.bytes_written(), 3);
+        assert_eq!(registry.total(), 8);
+    }
+
+    #[test]
+    fn registry_total_saturates() {
+        let registry = Registry::new();
+        let registration = Registration::new(&registry);
+        registration.read(usize::MAX);
+        registration.written(1);
+        assert_eq!(registry.total(), usize::MAX);
+    }
+
+    fn total<C: Counter>(counter: C) -> usize {
+        counter.count()
+    }
+
+    #[test]
+    fn nested() {
+        let mut v: Vec<u8> = Vec::new();
+
+        {
+            let mut inner = WriteCounter::from(&mut v);
+
+            {
+                let mut outer = WriteCounter::from(&mut inner);
+                outer.write_all(DATA).unwrap();
+                write!(outer, "{}", 1234).unwrap();
+                assert_eq!(total(&outer), DATA.len() + 4);
+                assert_eq!(total(&mut outer), DATA.len() + 4);
+            }
+
+            assert_eq!(total(&inner), DATA.len() + 4);
+
+            let boxed: Box<dyn Counter> = Box::new(inner);
+            assert_eq!(total(boxed), DATA.len() + 4);
+        }
+
+        assert_eq!(v.len(), DATA.len() + 4);
+    }
+
+    #[test]
+    fn count_nonzero() {
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d);
+        assert_eq!(r.count_nonzero(), None);
+
+        let mut b = [0u8; 2];
+        assert_eq!(r.read(&mut b).unwrap(), 2);
+        assert_eq!(r.count_nonzero(), NonZeroUsize::new(2));
+    }
+
+    fn consume(reader: &mut dyn Read, writer: &mut dyn Write) {
+        io::copy(reader, writer).unwrap();
+    }
+
+    #[test]
+    fn as_dyn() {
+        let mut d = DATA;
+        let mut v: Vec<u8> = Vec::new();
+        let mut r = ReadCounter::from(&mut d);
+        let mut w = WriteCounter::from(&mut v);
+
+        consume(r.as_dyn_read(), w.as_dyn_write());
+        assert_eq!(r.count(), DATA.len());
+        assert_eq!(w.count(), DATA.len());
+    }
+
+    struct Unflushable(bool);
+
+    impl Write for Unflushable {
+        fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+            Ok(buffer.len())
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            if self.0 {
+                return Err(io::Error::new(io::ErrorKind::Other, "flush failed"));
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn pending() {
+        let mut u = Unflushable(true);
+        let mut w = WriteCounter::from(&mut u);
+
+        w.write_all(DATA).unwrap();
+        assert_eq!(w.pending(), 3);
+        assert!(w.flush().is_err());
+        assert_eq!(w.pending(), 3);
+
+        w.write_all(DATA).unwrap();
+        assert_eq!(w.pending(), 6);
+
+        w.writer.0 = false;
+        w.flush().unwrap();
+        assert_eq!(w.pending(), 0);
+        assert_eq!(w.count(), 6);
+    }
+
+    #[test]
+    fn redundant_flushes() {
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = WriteCounter::from(&mut v);
+
+        w.flush().unwrap();
+        w.write_all(DATA).unwrap();
+        w.flush().unwrap();
+        w.flush().unwrap();
+        w.flush().unwrap();
+        w.write_all(DATA).unwrap();
+        w.flush().unwrap();
+
+        assert_eq!(w.flushes(), 5);
+        assert_eq!(w.redundant_flushes(), 2);
+    }
+
+    #[test]
+    fn line_count() {
+        let mut v = Vectored(Vec::new());
+        let mut w = WriteCounter::from(&mut v).with_line_count();
+
+        assert_eq!(w.write(b"a\nb\n").unwrap(), 2);
+        assert_eq!(w.line_count(), Some(1));
+
+        w.write_all(b"\n\nc\n").unwrap();
+        assert_eq!(w.line_count(), Some(4));
+
+        let buffers = [IoSlice::new(b"\nxy"), IoSlice::new(b"z\n\n")];
+        assert_eq!(w.write_vectored(&buffers).unwrap(), 5);
+        assert_eq!(w.line_count(), Some(6));
+        assert_eq!(w.count(), 11);
+
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = WriteCounter::from(&mut v);
+        w.write_all(b"a\nb\n").unwrap();
+        assert_eq!(w.line_count(), None);
+    }
+
+    #[test]
+    fn add() {
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d);
+        let mut b = [0u8; 2];
+
+        r.add(4);
+        assert_eq!(r.read(&mut b).unwrap(), 2);
+        r += 1;
+        assert_eq!(r.count(), 7);
+        assert_eq!(r.operations(), 1);
+        assert_eq!(r.offset_range_of_last_read(), 7..7);
+
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = WriteCounter::from(&mut v);
+        w += 2;
+        w.write_all(DATA).unwrap();
+        assert_eq!(w.count(), 5);
+        assert_eq!(w.pending(), 3);
+    }
+
+    #[test]
+    fn add_saturates() {
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d).with_count(usize::MAX - 1);
+        r.add(1);
+        assert_eq!(r.count(), usize::MAX);
+        r += 5;
+        assert_eq!(r.count(), usize::MAX);
+
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = WriteCounter::from(&mut v);
+        w.add(usize::MAX);
+        w += 1;
+        assert_eq!(w.count(), usize::MAX);
+    }
+
+    #[test]
+    fn on_threshold() {
+        let data = [0u8; 10];
+        let mut d = &data[..];
+        let fired = Mutex::new(Vec::new());
+        let mut r = ReadCounter::from(&mut d).on_threshold(2, |n| fired.lock().unwrap().push(n));
+        let mut b = [0u8; 1];
+        let mut big = [0u8; 5];
+
+        assert_eq!(r.read(&mut b).unwrap(), 1);
+        assert!(fired.lock().unwrap().is_empty());
+        assert_eq!(r.read(&mut big).unwrap(), 5);
+        assert_eq!(r.read(&mut big).unwrap(), 4);
+        drop(r);
+
+        assert_eq!(*fired.lock().unwrap(), vec![6]);
+    }
+
+    struct Interrupting<'a>(&'a [u8], bool);
+
+    impl<'a> Read for Interrupting<'a> {
+        fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+            if !self.1 {
+                self.1 = true;
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+            }
+
+            self.0.read(buffer)
+        }
+    }
+
+    #[test]
+    fn retry_interrupted() {
+        let mut b = [0u8; 4];
+
+        let mut i = Interrupting(DATA, false);
+        let mut r = ReadCounter::from(&mut i);
+        let err = r.read(&mut b).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert_eq!(r.operations(), 0);
+
+        let mut i = Interrupting(DATA, false);
+        let mut r = ReadCounter::from(&mut i).with_retry_interrupted();
+        assert_eq!(r.read(&mut b).unwrap(), 3);
+        assert_eq!(r.count(), 3);
+        assert_eq!(r.operations(), 1);
+    }
+
+    #[test]
+    fn count_si() {
+        assert_eq!(Fixed(0).count_si(), "0 B");
+        assert_eq!(Fixed(999).count_si(), "999 B");
+        assert_eq!(Fixed(1000).count_si(), "1 kB");
+        assert_eq!(Fixed(1500).count_si(), "1.5 kB");
+        assert_eq!(Fixed(1_234_567).count_si(), "1.23 MB");
+        assert_eq!(Fixed(999_999).count_si(), "1 MB");
+    }
+
+    #[test]
+    fn context() {
+        #[derive(Debug, PartialEq)]
+        struct Connection(u32);
+
+        let mut d = DATA;
+        let r = ReadCounter::from(&mut d).with_context(Connection(7));
+        assert_eq!(r.context::<Connection>(), Some(&Connection(7)));
+        assert_eq!(r.context::<u32>(), None);
+
+        let mut v: Vec<u8> = Vec::new();
+        let w = WriteCounter::from(&mut v)
+            .with_context(1u32)
+            .with_context("two");
+        assert_eq!(w.context::<u32>(), None);
+        assert_eq!(w.context::<&str>(), Some(&"two"));
+    }
+
+    struct Flaky<'a>(&'a [u8], Vec<io::ErrorKind>);
+
+    impl<'a> Read for Flaky<'a> {
+        fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+            match self.1.pop() {
+                Some(kind) => Err(io::Error::new(kind, "flaky")),
+                None => self.0.read(buffer),
+            }
+        }
+    }
+
+    #[test]
+    fn error_kinds() {
+        use std::io::ErrorKind::{Interrupted, WouldBlock};
+
+        let mut f = Flaky(
+            DATA,
+            vec![Interrupted, WouldBlock, Interrupted, Interrupted],
+        );
+        let mut r = ReadCounter::from(&mut f).with_retry_interrupted();
+        let mut b = [0u8; 4];
+
+        assert_eq!(r.read(&mut b).unwrap_err().kind(), WouldBlock);
+        assert_eq!(r.read(&mut b).unwrap(), 3);
+        assert_eq!(r.error_kinds().get(&Interrupted), Some(&3));
+        assert_eq!(r.error_kinds().get(&WouldBlock), Some(&1));
+        assert_eq!(r.error_kinds().len(), 2);
+        assert_eq!(r.count(), 3);
+    }
+
+    #[test]
+    fn read_exact_vec() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut d = &data[..];
+        let mut r = ReadCounter::from(&mut d);
+
+        assert_eq!(r.read_exact_vec(4).unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(r.count(), 4);
+
+        let err = r.read_exact_vec(8).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(r.count(), 10);
+    }
+
+    #[test]
+    fn error_on_eof() {
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d).with_error_on_eof();
+        let mut b = [0u8; 2];
+
+        assert_eq!(r.read(&mut b).unwrap(), 2);
+        assert_eq!(r.read(&mut b).unwrap(), 1);
+        let err = r.read(&mut b).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(r.read(&mut []).unwrap(), 0);
+        assert_eq!(r.count(), 3);
+        assert_eq!(r.operations(), 2);
+    }
+
+    #[test]
+    fn read_header_then_body() {
+        let data = [0u8, 3, b'a', b'b', b'c'];
+        let mut d = &data[..];
+
+        let (len, mut r) = super::read_header_then_body(&mut d, |reader| {
+            let mut header = [0u8; 2];
+            reader.read_exact(&mut header)?;
+            Ok(u16::from_be_bytes(header))
+        })
+        .unwrap();
+        assert_eq!(len, 3);
+        assert_eq!(r.count(), 0);
+
+        let mut body = Vec::new();
+        r.read_to_end(&mut body).unwrap();
+        assert_eq!(body, b"abc");
+        assert_eq!(r.count(), 3);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = WriteCounter::from(&mut v);
+        w.write_all(DATA).unwrap();
+        let w = Fixed(w.count());
+
+        let mut d = &v[..];
+        let mut r = ReadCounter::from(&mut d);
+        let mut b = [0u8; 2];
+        r.read_exact(&mut b).unwrap();
+
+        let err = super::assert_round_trip(&w, &r).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("3 bytes written, 2 bytes read"));
+
+        r.read_to_end(&mut Vec::new()).unwrap();
+        super::assert_round_trip(&w, &r).unwrap();
+    }
+
+    #[test]
+    fn read_into() {
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d);
+        let mut b = [0u8; 2];
+        let mut total = 5;
+
+        assert_eq!(r.read_into(&mut b, &mut total).unwrap(), 2);
+        assert_eq!(r.read_into(&mut b, &mut total).unwrap(), 1);
+        assert_eq!(r.read_into(&mut b, &mut total).unwrap(), 0);
+        assert_eq!(total, 8);
+        assert_eq!(r.count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn child() {
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut w = WriteCounter::from_child_stdin(&mut child).unwrap();
+        w.write_all(b"hello").unwrap();
+        assert_eq!(w.count(), 5);
+        drop(w);
+
+        let err = WriteCounter::from_child_stdin(&mut child).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let mut out = Vec::new();
+        let mut r = ReadCounter::from_child_stdout(&mut child).unwrap();
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(&out[..], b"hello");
+        assert_eq!(r.count(), 5);
+        drop(r);
+
+        assert!(child.wait().unwrap().success());
+    }
+
+    #[test]
+    fn finalize() {
+        let report = {
+            let mut v: Vec<u8> = Vec::new();
+            let mut w = WriteCounter::from(&mut v).with_line_count();
+            w.write_all(b"a\nb\n").unwrap();
+            w.finalize()
+        };
+
+        assert_eq!(report.bytes, 4);
+        assert_eq!(report.ops, 1);
+        assert_eq!(report.lines, Some(2));
+        assert_eq!(report.pending, Some(4));
+
+        let mut f = Flaky(DATA, vec![io::ErrorKind::Interrupted]);
+        let mut r = ReadCounter::from(&mut f).with_retry_interrupted();
+        let mut b = [0u8; 4];
+        assert_eq!(r.read(&mut b).unwrap(), 3);
+
+        let report = r.finalize();
+        assert_eq!(report.bytes, 3);
+        assert_eq!(report.ops, 1);
+        assert_eq!(report.lines, None);
+        assert_eq!(
+            report.error_kinds.get(&io::ErrorKind::Interrupted),
+            Some(&1)
+        );
+    }
+
+    #[test]
+    fn option() {
+        assert_eq!(Some(Fixed(3)).count(), 3);
+        assert_eq!(None::<Fixed>.count(), 0);
+
+        let mut d = DATA;
+        let mut r = Some(ReadCounter::from(&mut d));
+        if let Some(ref mut r) = r {
+            r.read_to_end(&mut Vec::new()).unwrap();
+        }
+        assert_eq!(r.count(), 3);
+    }
+
+    #[test]
+    fn resume() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut d = &data[..];
+        let mut r = ReadCounter::from(&mut d);
+        let mut b = [0u8; 4];
+
+        r.read_exact(&mut b).unwrap();
+        let token = r.resume_token();
+        assert_eq!(token, 4);
+        drop(r);
+
+        let mut rest = &data[token as usize..];
+        let mut r = ReadCounter::resume(&mut rest, token).unwrap();
+        r.read_exact(&mut b).unwrap();
+        assert_eq!(b, [4, 5, 6, 7]);
+        assert_eq!(r.count(), 8);
+        assert_eq!(r.resume_token(), 8);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn resume_overflow() {
+        let mut d = DATA;
+        let err = ReadCounter::resume(&mut d, u64::MAX).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn eta() {
+        use std::thread::sleep;
+
+        let data = [0u8; 30];
+        let mut d = &data[..];
+        let mut r = ReadCounter::from(&mut d).with_expected_total(30);
+        let mut b = [0u8; 10];
+        assert_eq!(r.eta(), None);
+
+        sleep(Duration::from_millis(20));
+        r.read_exact(&mut b).unwrap();
+        assert!(r.eta().unwrap() >= Duration::from_millis(30));
+
+        sleep(Duration::from_millis(20));
+        r.read_exact(&mut b).unwrap();
+        assert!(r.eta().unwrap() >= Duration::from_millis(15));
+
+        r.read_exact(&mut b).unwrap();
+        assert_eq!(r.eta(), Some(Duration::from_secs(0)));
+        drop(r);
+
+        assert_eq!(ReadCounter::from(&mut d).eta(), None);
+    }
+
+    #[test]
+    fn set_waker() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::task::Wake;
+
+        struct Wakes(AtomicUsize);
+
+        impl Wake for Wakes {
+            fn wake(self: Arc<Self>) {
+                self.wake_by_ref();
+            }
+
+            fn wake_by_ref(self: &Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let wakes = Arc::new(Wakes(AtomicUsize::new(0)));
+        let mut d = DATA;
+        let mut r = ReadCounter::from(&mut d);
+        r.set_waker(Waker::from(wakes.clone()));
+
+        let mut b = [0u8; 2];
+        assert_eq!(r.read(&mut b).unwrap(), 2);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+        assert_eq!(r.read(&mut b).unwrap(), 1);
+        assert_eq!(r.read(&mut b).unwrap(), 0);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn annotate_error() {
+        let data = b"one\ntwo\nth";
+        let mut d = &data[..];
+        let mut r = ReadCounter::from(&mut d).with_line_count();
+
+        let mut b = [0u8; 10];
+        r.read_exact(&mut b).unwrap();
+        assert_eq!(r.line_count(), Some(2));
+        assert!(r.annotate_error(Ok(1)).is_ok());
+
+        let parsed: io::Result<()> = Err(io::Error::new(io::ErrorKind::InvalidData, "bad token"));
+        let err = r.annotate_error(parsed).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "bad token at byte 10, line 3");
+    }
+
+    #[test]
+    fn annotate_error_source() {
+        let data = b"one\ntwo";
+        let mut d = &data[..];
+        let mut r = ReadCounter::from(&mut d);
+
+        let mut b = [0u8; 7];
+        r.read_exact(&mut b).unwrap();
+        assert_eq!(r.line_count(), None);
+
+        let quota = QuotaExceeded { quota: 8 };
+        let failed: io::Result<()> = Err(io::Error::new(io::ErrorKind::Other, quota));
+        let err = r.annotate_error(failed).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert_eq!(err.to_string(), "write quota of 8 bytes exceeded at byte 7");
+
+        let source = err.get_ref().unwrap().source().unwrap();
+        let inner = source.downcast_ref::<io::Error>().unwrap();
+        let payload = inner.get_ref().unwrap().downcast_ref::<QuotaExceeded>();
+        assert_eq!(payload, Some(&quota));
+    }
+
+    #[test]
+    fn checkpoints() {
+        let data = [0u8; 10];
+        let mut d = &data[..];
+        let mut r = ReadCounter::from(&mut d);
+        let mut b = [0u8; 2];
+
+        r.read_exact(&mut b).unwrap();
+        r.push_checkpoint();
+        r.read_exact(&mut b).unwrap();
+        r.push_checkpoint();
+        r.read_exact(&mut b).unwrap();
+        r.read_exact(&mut b).unwrap();
+        assert_eq!(r.pop_checkpoint(), Some(4));
+        r.read_exact(&mut b).unwrap();
+        assert_eq!(r.pop_checkpoint(), Some(8));
+    }
+
+    #[test]
+    fn pop_checkpoint_empty() {
+        let mut d = DATA;
+        assert_eq!(ReadCounter::from(&mut d).pop_checkpoint(), None);
+    }
+
+    #[test]
+    fn send() {
+        fn _assert_send<T: Send>() {}
+
+        _assert_send::<ReadCounter<'static, std::fs::File>>();
+        _assert_send::<WriteCounter<'static, std::fs::File>>();
+    }
+
+    #[test]
+    fn account() {
+        let mut count = 0;
+
+        assert_eq!(account_read(&mut count, Ok(3)).unwrap(), 3);
+        assert_eq!(account_write(&mut count, Ok(2)).unwrap(), 2);
+        assert_eq!(count, 5);
+
+        let err = io::Error::new(io::ErrorKind::Other, "failed");
+        assert!(account_read(&mut count, Err(err)).is_err());
+        let err = io::Error::new(io::ErrorKind::Other, "failed");
+        assert!(account_write(&mut count, Err(err)).is_err());
+        assert_eq!(count, 5);
     }
 }
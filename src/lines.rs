@@ -0,0 +1,133 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::BufRead`, yielding its lines and counting the bytes
+/// consumed.
+///
+/// A `CountingLines` behaves just like `BufRead::lines()`, yielding each line without its `\n` or
+/// `\r\n` terminator. However, the bytes of the stripped terminators are still counted, so
+/// `count()` reports the exact number of bytes consumed from the reader. The number of lines
+/// yielded is available from `lines()`.
+///
+/// Since `Iterator` also has a `count` method (which consumes the iterator), call
+/// `Counter::count(&counter)` to get the number of bytes consumed so far.
+pub struct CountingLines<B: io::BufRead> {
+    reader: B,
+    count: usize,
+    lines: usize,
+}
+
+impl<B: io::BufRead> From<B> for CountingLines<B> {
+    /// Creates a `CountingLines` by wrapping any implementation of `std::io::BufRead`.
+    fn from(value: B) -> CountingLines<B> {
+        CountingLines {
+            reader: value,
+            count: 0,
+            lines: 0,
+        }
+    }
+}
+
+impl<B: io::BufRead> CountingLines<B> {
+    /// Returns the number of lines yielded so far.
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+}
+
+impl<B: io::BufRead> Counter for CountingLines<B> {
+    /// Returns the number of bytes consumed so far, including line terminators.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<B: io::BufRead> Iterator for CountingLines<B> {
+    type Item = io::Result<String>;
+
+    /// Reads the next line, counting the bytes consumed along the way.
+    ///
+    /// A line that is not valid UTF-8 yields an `InvalidData` error, but its bytes are still
+    /// counted and it is not included in `lines()`.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `next`.
+    fn next(&mut self) -> Option<io::Result<String>> {
+        let mut line = Vec::new();
+
+        match self.reader.read_until(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(size) => {
+                self.count += size;
+
+                if line.ends_with(b"\n") {
+                    line.pop();
+                    if line.ends_with(b"\r") {
+                        line.pop();
+                    }
+                }
+
+                match String::from_utf8(line) {
+                    Ok(line) => {
+                        self.lines += 1;
+                        Some(Ok(line))
+                    }
+                    Err(e) => Some(Err(io::Error::new(io::ErrorKind::InvalidData, e))),
+                }
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines() {
+        let data = b"one\ntwo\r\n\nfour";
+        let mut l = CountingLines::from(&data[..]);
+
+        let lines: Vec<String> = l.by_ref().map(Result::unwrap).collect();
+        assert_eq!(lines, vec!["one", "two", "", "four"]);
+        assert_eq!(l.lines(), 4);
+        assert_eq!(Counter::count(&l), data.len());
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let data = b"one\n\xff\ntwo";
+        let mut l = CountingLines::from(&data[..]);
+
+        assert_eq!(l.next().unwrap().unwrap(), "one");
+        let err = l.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(Counter::count(&l), 6);
+        assert_eq!(l.next().unwrap().unwrap(), "two");
+        assert!(l.next().is_none());
+        assert_eq!(l.lines(), 2);
+        assert_eq!(Counter::count(&l), data.len());
+    }
+}
@@ -0,0 +1,119 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and checking that the
+/// stream delivered at least a minimum number of them.
+///
+/// A `MinLengthReadCounter` behaves just like a `ReadCounter`. Once the stream has been drained,
+/// call `verify()` to check that it was not truncated.
+pub struct MinLengthReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    minimum: usize,
+}
+
+impl<'a, T: 'a + io::Read> MinLengthReadCounter<'a, T> {
+    /// Creates a `MinLengthReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, minimum: usize) -> MinLengthReadCounter<'a, T> {
+        MinLengthReadCounter {
+            reader,
+            count: 0,
+            minimum,
+        }
+    }
+
+    /// Returns the minimum number of bytes the stream must deliver.
+    pub fn minimum(&self) -> usize {
+        self.minimum
+    }
+
+    /// Checks that at least the minimum number of bytes has been read.
+    ///
+    /// This is intended to be called after the stream has been read to the end.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error of kind `UnexpectedEof` if fewer than the minimum number
+    /// of bytes have been read.
+    pub fn verify(&self) -> io::Result<()> {
+        if self.count < self.minimum {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stream was shorter than the minimum length",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for MinLengthReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for MinLengthReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn verify() {
+        let data = [1u8, 2, 3];
+        let mut out = Vec::new();
+
+        let mut d = &data[..];
+        let mut r = MinLengthReadCounter::new(&mut d, 3);
+        r.read_to_end(&mut out).unwrap();
+        r.verify().unwrap();
+
+        let mut d = &data[..];
+        let mut r = MinLengthReadCounter::new(&mut d, 4);
+        r.read_to_end(&mut out).unwrap();
+        let err = r.verify().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(r.count(), 3);
+    }
+}
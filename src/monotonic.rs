@@ -0,0 +1,135 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[cfg(debug_assertions)]
+use std::mem;
+#[cfg(debug_assertions)]
+use std::sync::Mutex;
+
+use Counter;
+
+/// Wraps any implementation of `Counter`, checking that its count never decreases.
+///
+/// In debug builds, a `MonotonicCounter` records the last observed count and asserts that each
+/// call to `count()` returns a value no smaller than the previous one. This catches bugs in custom
+/// `Counter` implementations. In release builds, it is a zero-cost passthrough. The last observed
+/// count is kept behind a `Mutex`, held while the wrapped counter is queried, so concurrent calls
+/// to `count()` cannot report an older reading after a newer one. `MonotonicCounter<C>` is `Sync`
+/// whenever `C` is, in every build.
+pub struct MonotonicCounter<C: Counter> {
+    inner: C,
+    #[cfg(debug_assertions)]
+    last: Mutex<usize>,
+}
+
+impl<C: Counter> From<C> for MonotonicCounter<C> {
+    /// Creates a `MonotonicCounter` by wrapping any implementation of `Counter`.
+    fn from(value: C) -> MonotonicCounter<C> {
+        MonotonicCounter {
+            inner: value,
+            #[cfg(debug_assertions)]
+            last: Mutex::new(0),
+        }
+    }
+}
+
+impl<C: Counter> MonotonicCounter<C> {
+    /// Returns a reference to the wrapped counter.
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped counter.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Unwraps this instance, returning the wrapped counter.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C: Counter> Counter for MonotonicCounter<C> {
+    /// Returns the count of the wrapped counter.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if the count is less than the previously observed count.
+    #[cfg(debug_assertions)]
+    fn count(&self) -> usize {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let count = self.inner.count();
+        let previous = mem::replace(&mut *last, count);
+        drop(last);
+
+        debug_assert!(
+            count >= previous,
+            "count decreased from {} to {}",
+            previous,
+            count
+        );
+        count
+    }
+
+    /// Returns the count of the wrapped counter.
+    #[cfg(not(debug_assertions))]
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    #[cfg(debug_assertions)]
+    use testutil::Fixed;
+    use ReadCounter;
+
+    #[test]
+    fn increasing() {
+        let data = [1u8, 2, 3];
+        let mut d = &data[..];
+        let mut m = MonotonicCounter::from(ReadCounter::from(&mut d));
+        let mut buf = [0u8; 2];
+
+        assert_eq!(m.count(), 0);
+        assert_eq!(m.get_mut().read(&mut buf).unwrap(), 2);
+        assert_eq!(m.count(), 2);
+        assert_eq!(m.count(), 2);
+    }
+
+    #[test]
+    fn sync() {
+        fn _assert_sync<T: Sync>() {}
+
+        _assert_sync::<MonotonicCounter<Box<dyn Counter + Sync>>>();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "count decreased")]
+    fn decreasing() {
+        let mut m = MonotonicCounter::from(Fixed(5));
+        assert_eq!(m.count(), 5);
+
+        m.get_mut().0 = 4;
+        let _ = m.count();
+    }
+}
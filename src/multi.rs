@@ -0,0 +1,136 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Writes to several implementations of `std::io::Write` at once, counting the bytes written.
+///
+/// A `MultiWriteCounter` fans out each buffer to every wrapped sink, in order, and counts the
+/// bytes once. Each buffer is written to each sink in full (using `write_all`), so all sinks
+/// receive identical data.
+///
+/// If a sink fails, the error is returned immediately and the bytes are not counted. The sinks
+/// before the failing one have already received the whole buffer, while the sinks after it have
+/// received none of it. The failing sink may have received part of it.
+pub struct MultiWriteCounter<'a> {
+    writers: Vec<&'a mut dyn io::Write>,
+    count: usize,
+}
+
+impl<'a> From<Vec<&'a mut dyn io::Write>> for MultiWriteCounter<'a> {
+    /// Creates a `MultiWriteCounter` by wrapping any number of `std::io::Write` implementations.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instances.
+    fn from(value: Vec<&'a mut dyn io::Write>) -> MultiWriteCounter<'a> {
+        MultiWriteCounter {
+            writers: value,
+            count: 0,
+        }
+    }
+}
+
+impl<'a> Counter for MultiWriteCounter<'a> {
+    /// Returns the number of bytes written so far (to each sink).
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a> io::Write for MultiWriteCounter<'a> {
+    /// Writes the whole buffer to each sink in turn, counting the bytes written.
+    ///
+    /// # Panics
+    ///
+    ///   1. When any underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if any underlying function errors.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        for writer in &mut self.writers {
+            writer.write_all(buffer)?;
+        }
+
+        self.count += buffer.len();
+        Ok(buffer.len())
+    }
+
+    /// Flushes each sink in turn.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        for writer in &mut self.writers {
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> Result<usize, io::Error> {
+            Err(io::Error::new(io::ErrorKind::Other, "broken"))
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn fan_out() {
+        let mut a: Vec<u8> = Vec::new();
+        let mut b: Vec<u8> = Vec::new();
+
+        {
+            let mut w = MultiWriteCounter::from(vec![&mut a as &mut dyn Write, &mut b]);
+            w.write_all(b"hello").unwrap();
+            write!(w, " {}", 42).unwrap();
+            w.flush().unwrap();
+            assert_eq!(w.count(), 8);
+        }
+
+        assert_eq!(&a[..], b"hello 42");
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn failure() {
+        let mut a: Vec<u8> = Vec::new();
+        let mut b = Broken;
+        let mut c: Vec<u8> = Vec::new();
+
+        {
+            let sinks = vec![&mut a as &mut dyn Write, &mut b, &mut c];
+            let mut w = MultiWriteCounter::from(sinks);
+            assert!(w.write(b"data").is_err());
+            assert_eq!(w.count(), 0);
+        }
+
+        assert_eq!(&a[..], b"data");
+        assert!(c.is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+
+use Counter;
+
+// A file which is only opened when it is first read.
+struct LazyFile {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl io::Read for LazyFile {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        if self.file.is_none() {
+            self.file = Some(File::open(&self.path)?);
+        }
+
+        match self.file {
+            Some(ref mut file) => file.read(buffer),
+            None => Ok(0),
+        }
+    }
+}
+
+/// Reads several sources in order as one stream, counting the bytes read from each.
+///
+/// A `MultiFileReadCounter` reads each source to its end before moving on to the next, so the
+/// transitions between them are seamless: `read` only returns `Ok(0)` once every source has been
+/// exhausted. It counts both the total number of bytes read and the number read from each source.
+/// Files given by path are opened one at a time, when they are first read. Each source is dropped
+/// as soon as it is exhausted, so at most one file is open at a time.
+pub struct MultiFileReadCounter<'a> {
+    sources: Vec<Box<dyn io::Read + 'a>>,
+    counts: Vec<usize>,
+    current: usize,
+    count: usize,
+}
+
+impl<'a> From<Vec<Box<dyn io::Read + 'a>>> for MultiFileReadCounter<'a> {
+    /// Creates a `MultiFileReadCounter` which reads the given sources in order.
+    fn from(value: Vec<Box<dyn io::Read + 'a>>) -> MultiFileReadCounter<'a> {
+        MultiFileReadCounter {
+            counts: vec![0; value.len()],
+            sources: value,
+            current: 0,
+            count: 0,
+        }
+    }
+}
+
+impl MultiFileReadCounter<'static> {
+    /// Creates a `MultiFileReadCounter` which reads the files at the given paths in order.
+    ///
+    /// Each file is opened when it is first read, so an error opening it is returned by `read`.
+    #[must_use]
+    pub fn open(paths: Vec<PathBuf>) -> MultiFileReadCounter<'static> {
+        let sources = paths
+            .into_iter()
+            .map(|path| Box::new(LazyFile { path, file: None }) as Box<dyn io::Read>)
+            .collect::<Vec<_>>();
+
+        MultiFileReadCounter::from(sources)
+    }
+}
+
+impl<'a> MultiFileReadCounter<'a> {
+    /// Returns the total number of bytes read so far, across all sources.
+    pub fn total_count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the index of the source currently being read.
+    ///
+    /// Once every source has been exhausted, this is the number of sources.
+    pub fn current_file_index(&self) -> usize {
+        self.current
+    }
+
+    /// Returns the number of bytes read so far from each source, in order.
+    pub fn file_counts(&self) -> &[usize] {
+        &self.counts
+    }
+}
+
+impl<'a> Counter for MultiFileReadCounter<'a> {
+    /// Returns the total number of bytes read so far, across all sources.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a> io::Read for MultiFileReadCounter<'a> {
+    /// Reads from the current source, moving on to the next one at its end.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors, including when a file cannot
+    /// be opened. The current source is not skipped in that case.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        while self.current < self.sources.len() {
+            let size = self.sources[self.current].read(buffer)?;
+            if size == 0 && !buffer.is_empty() {
+                self.sources[self.current] = Box::new(io::empty());
+                self.current += 1;
+                continue;
+            }
+
+            self.counts[self.current] += size;
+            self.count += size;
+            return Ok(size);
+        }
+
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::io::Read;
+
+    #[test]
+    fn concatenate() {
+        let sources: Vec<Box<dyn Read>> = vec![
+            Box::new(&b"abc"[..]),
+            Box::new(&b""[..]),
+            Box::new(&b"defgh"[..]),
+        ];
+        let mut r = MultiFileReadCounter::from(sources);
+
+        let mut buffer = [0u8; 4];
+        assert_eq!(r.read(&mut buffer).unwrap(), 3);
+        assert_eq!(r.current_file_index(), 0);
+
+        let mut data = b"abc".to_vec();
+        r.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"abcdefgh");
+        assert_eq!(r.current_file_index(), 3);
+        assert_eq!(r.file_counts(), &[3, 0, 5]);
+        assert_eq!(r.total_count(), 8);
+    }
+
+    struct Dropped<'a>(&'a Cell<bool>);
+
+    impl<'a> Read for Dropped<'a> {
+        fn read(&mut self, _: &mut [u8]) -> Result<usize, io::Error> {
+            Ok(0)
+        }
+    }
+
+    impl<'a> Drop for Dropped<'a> {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    #[test]
+    fn drops_exhausted() {
+        let dropped = Cell::new(false);
+        let sources: Vec<Box<dyn Read>> = vec![Box::new(Dropped(&dropped)), Box::new(&b"ab"[..])];
+        let mut r = MultiFileReadCounter::from(sources);
+
+        let mut buffer = [0u8; 1];
+        assert_eq!(r.read(&mut buffer).unwrap(), 1);
+        assert!(dropped.get());
+        assert_eq!(r.current_file_index(), 1);
+    }
+
+    #[test]
+    fn open() {
+        let path = std::env::temp_dir().join(format!("voncount-{}", std::process::id()));
+        std::fs::write(&path, b"file").unwrap();
+
+        let mut r = MultiFileReadCounter::open(vec![path.clone(), path.clone()]);
+        let mut data = Vec::new();
+        r.read_to_end(&mut data).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(data, b"filefile");
+        assert_eq!(r.file_counts(), &[4, 4]);
+    }
+}
@@ -0,0 +1,144 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use {Counter, TransformCounter};
+
+/// Wraps any implementation of `std::io::Read`, normalizing line endings and counting the bytes.
+///
+/// A `NormalizeReadCounter` converts both `\r\n` and a lone `\r` into `\n` as data is delivered.
+/// It counts both the bytes consumed from the wrapped reader and the normalized bytes delivered to
+/// the caller. A `\r` is translated as soon as it is read; if the next byte (possibly from the
+/// next read) is `\n`, that byte is dropped. So a `\r\n` split across reads is still normalized to
+/// a single `\n`.
+pub struct NormalizeReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    input: usize,
+    output: usize,
+    cr: bool,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for NormalizeReadCounter<'a, T> {
+    /// Creates a `NormalizeReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> NormalizeReadCounter<'a, T> {
+        NormalizeReadCounter {
+            reader: value,
+            input: 0,
+            output: 0,
+            cr: false,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> NormalizeReadCounter<'a, T> {
+    /// Returns the number of bytes consumed from the wrapped reader so far.
+    pub fn input_count(&self) -> usize {
+        self.input
+    }
+
+    /// Returns the number of normalized bytes delivered to the caller so far.
+    pub fn output_count(&self) -> usize {
+        self.output
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for NormalizeReadCounter<'a, T> {
+    /// Returns the number of normalized bytes delivered to the caller so far.
+    fn count(&self) -> usize {
+        self.output
+    }
+}
+
+impl<'a, T: 'a + io::Read> TransformCounter for NormalizeReadCounter<'a, T> {
+    /// Returns the number of bytes consumed from the wrapped reader so far.
+    fn input_count(&self) -> usize {
+        self.input
+    }
+
+    /// Returns the number of normalized bytes delivered to the caller so far.
+    fn output_count(&self) -> usize {
+        self.output
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for NormalizeReadCounter<'a, T> {
+    /// Reads from the inner reader, normalizing line endings and counting the bytes.
+    ///
+    /// This function calls the inner `read` function again if a read yields only the `\n` of a
+    /// `\r\n` pair, so `Ok(0)` still means the end of the stream.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        loop {
+            let size = self.reader.read(buffer)?;
+            self.input += size;
+
+            // The output is never longer than the input, so normalize in place.
+            let mut pos = 0;
+            for i in 0..size {
+                let byte = buffer[i];
+                let cr = self.cr;
+                self.cr = byte == b'\r';
+
+                if byte == b'\n' && cr {
+                    continue;
+                }
+
+                buffer[pos] = if self.cr { b'\n' } else { byte };
+                pos += 1;
+            }
+
+            if pos > 0 || size == 0 {
+                self.output += pos;
+                return Ok(pos);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use testutil::Chunked;
+
+    #[test]
+    fn split() {
+        // The first read ends with the `\r` of a pair and the second starts with its `\n`.
+        let input = b"ab\r\ncd\r\ref\r\rg\r";
+        let mut reader = Chunked(input, 3);
+        let mut counter = NormalizeReadCounter::from(&mut reader);
+
+        let mut data = Vec::new();
+        counter.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"ab\ncd\n\nef\n\ng\n");
+        assert_eq!(counter.input_count(), input.len());
+        assert_eq!(counter.output_count(), data.len());
+        assert_eq!(counter.count(), 13);
+    }
+}
@@ -0,0 +1,156 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Write` and `std::io::Seek`, checking that writes are
+/// contiguous.
+///
+/// An `OffsetWriteCounter` tracks the region written so far, which starts at offset zero. Each
+/// write must begin exactly at the end of that region. A write which would leave a gap or overlap
+/// data already written fails with an error of kind `InvalidInput`, without reaching the wrapped
+/// writer. This catches sparse-write bugs in code which seeks before writing, such as output
+/// written with `pwrite`-style offsets.
+///
+/// The position is tracked through this instance's `Seek` implementation, so the wrapped writer
+/// should not be moved by other means.
+pub struct OffsetWriteCounter<'a, T: 'a + io::Write + io::Seek> {
+    writer: &'a mut T,
+    count: usize,
+    position: Option<u64>,
+}
+
+impl<'a, T: 'a + io::Write + io::Seek> From<&'a mut T> for OffsetWriteCounter<'a, T> {
+    /// Creates an `OffsetWriteCounter` by wrapping any implementation of `std::io::Write` and
+    /// `std::io::Seek`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> OffsetWriteCounter<'a, T> {
+        OffsetWriteCounter {
+            writer: value,
+            count: 0,
+            position: None,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Write + io::Seek> OffsetWriteCounter<'a, T> {
+    /// Returns the length of the contiguous region written so far, starting at offset zero.
+    ///
+    /// This is also the offset at which the next write must begin.
+    pub fn coverage(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Write + io::Seek> Counter for OffsetWriteCounter<'a, T> {
+    /// Returns the number of bytes written so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Write + io::Seek> io::Write for OffsetWriteCounter<'a, T> {
+    /// Checks the current offset, then proxies to the inner `write` function, counting the bytes
+    /// written.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors. It will also return an error
+    /// of kind `InvalidInput` if the write would not begin at the end of the region written so
+    /// far.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        let position = match self.position {
+            Some(position) => position,
+            None => self.writer.stream_position()?,
+        };
+        self.position = Some(position);
+
+        let coverage = self.count as u64;
+        if !buffer.is_empty() && position != coverage {
+            let msg = if position > coverage {
+                format!(
+                    "write at offset {} would leave a gap at {}",
+                    position, coverage
+                )
+            } else {
+                format!(
+                    "write at offset {} would overlap data before {}",
+                    position, coverage
+                )
+            };
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+
+        let size = self.writer.write(buffer)?;
+        self.position = Some(position + size as u64);
+        self.count += size;
+        Ok(size)
+    }
+
+    /// Proxies to the inner `flush` function.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.writer.flush()
+    }
+}
+
+impl<'a, T: 'a + io::Write + io::Seek> io::Seek for OffsetWriteCounter<'a, T> {
+    /// Proxies to the inner `seek` function, tracking the new position.
+    fn seek(&mut self, pos: io::SeekFrom) -> Result<u64, io::Error> {
+        self.position = None;
+        let position = self.writer.seek(pos)?;
+        self.position = Some(position);
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Seek, SeekFrom, Write};
+
+    #[test]
+    fn contiguous() {
+        let mut c = Cursor::new(Vec::new());
+        let mut w = OffsetWriteCounter::from(&mut c);
+
+        w.write_all(b"abc").unwrap();
+        w.seek(SeekFrom::Start(3)).unwrap();
+        w.write_all(b"def").unwrap();
+        assert_eq!(w.coverage(), 6);
+
+        w.seek(SeekFrom::Start(8)).unwrap();
+        let err = w.write_all(b"gap").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        w.seek(SeekFrom::Start(4)).unwrap();
+        let err = w.write_all(b"overlap").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        assert_eq!(w.coverage(), 6);
+        assert_eq!(w.count(), 6);
+        assert_eq!(c.into_inner(), b"abcdef");
+    }
+}
@@ -0,0 +1,113 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read within and beyond a
+/// limit.
+///
+/// An `OverflowCountReadCounter` delivers every byte to the caller, just like a `ReadCounter`.
+/// However, it splits the count at `limit`: bytes up to the limit are reported by
+/// `within_limit()` and any further bytes by `overflow()`. Unlike `Read::take()`, reaching the
+/// limit is not an error and does not end the stream. This measures how much a stream exceeded a
+/// budget while still draining it.
+pub struct OverflowCountReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    limit: usize,
+}
+
+impl<'a, T: 'a + io::Read> OverflowCountReadCounter<'a, T> {
+    /// Creates an `OverflowCountReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, limit: usize) -> OverflowCountReadCounter<'a, T> {
+        OverflowCountReadCounter {
+            reader,
+            count: 0,
+            limit,
+        }
+    }
+
+    /// Returns the limit.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the number of bytes read so far which are within the limit.
+    pub fn within_limit(&self) -> usize {
+        self.count.min(self.limit)
+    }
+
+    /// Returns the number of bytes read so far beyond the limit.
+    pub fn overflow(&self) -> usize {
+        self.count.saturating_sub(self.limit)
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for OverflowCountReadCounter<'a, T> {
+    /// Returns the total number of bytes read so far, including any overflow.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for OverflowCountReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn overflow() {
+        let data = [0u8; 10];
+        let mut d = &data[..];
+        let mut r = OverflowCountReadCounter::new(&mut d, 4);
+
+        let mut buffer = [0u8; 3];
+        r.read_exact(&mut buffer).unwrap();
+        assert_eq!(r.within_limit(), 3);
+        assert_eq!(r.overflow(), 0);
+
+        let mut rest = Vec::new();
+        assert_eq!(r.read_to_end(&mut rest).unwrap(), 7);
+        assert_eq!(r.within_limit(), 4);
+        assert_eq!(r.overflow(), 6);
+        assert_eq!(r.count(), 10);
+    }
+}
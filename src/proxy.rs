@@ -0,0 +1,264 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use Counter;
+
+// Returns whether an error just means that the stream is not ready yet.
+fn not_ready(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+// The state of one direction of a relay: a chunk read from the source but not yet written.
+struct Pipe {
+    buffer: Vec<u8>,
+    start: usize,
+    end: usize,
+    open: bool,
+}
+
+impl Pipe {
+    fn new() -> Pipe {
+        Pipe {
+            buffer: vec![0u8; 8192],
+            start: 0,
+            end: 0,
+            open: true,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.open || self.start < self.end
+    }
+
+    // Makes as much progress as possible without blocking, adding the bytes written to `count`.
+    //
+    // Returns whether any progress was made. A chunk is only read from `source` once the previous
+    // one has been completely written to `sink`, so no bytes are lost if `sink` is not ready.
+    fn pump<R: io::Read, W: io::Write>(
+        &mut self,
+        source: &mut R,
+        sink: &mut W,
+        count: &mut usize,
+    ) -> io::Result<bool> {
+        let mut progress = false;
+
+        loop {
+            if self.start < self.end {
+                match sink.write(&self.buffer[self.start..self.end]) {
+                    Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                    Ok(size) => {
+                        self.start += size;
+                        *count += size;
+                        progress = true;
+                        continue;
+                    }
+                    Err(ref e) if not_ready(e) => return Ok(progress),
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if !self.open {
+                return Ok(progress);
+            }
+
+            match source.read(&mut self.buffer) {
+                Ok(0) => {
+                    self.open = false;
+                    sink.flush()?;
+                    return Ok(true);
+                }
+                Ok(size) => {
+                    self.start = 0;
+                    self.end = size;
+                    progress = true;
+                }
+                Err(ref e) if not_ready(e) => return Ok(progress),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Relays bytes between a client and a server stream, counting the bytes forwarded each way.
+///
+/// A `ProxyCounter` packages the accounting for a simple proxy. `relay()` copies data in both
+/// directions until both streams reach the end, and the bytes forwarded each way are available
+/// from `client_to_server()` and `server_to_client()`. `count()` reports the total of both.
+pub struct ProxyCounter<'a, C: 'a + io::Read + io::Write, S: 'a + io::Read + io::Write> {
+    client: &'a mut C,
+    server: &'a mut S,
+    to_server: usize,
+    to_client: usize,
+}
+
+impl<'a, C: 'a + io::Read + io::Write, S: 'a + io::Read + io::Write> ProxyCounter<'a, C, S> {
+    /// Creates a `ProxyCounter` relaying between a client and a server stream.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instances.
+    #[must_use]
+    pub fn new(client: &'a mut C, server: &'a mut S) -> ProxyCounter<'a, C, S> {
+        ProxyCounter {
+            client,
+            server,
+            to_server: 0,
+            to_client: 0,
+        }
+    }
+
+    /// Returns the number of bytes forwarded from the client to the server so far.
+    pub fn client_to_server(&self) -> usize {
+        self.to_server
+    }
+
+    /// Returns the number of bytes forwarded from the server to the client so far.
+    pub fn server_to_client(&self) -> usize {
+        self.to_client
+    }
+
+    /// Copies data in both directions until both streams reach the end.
+    ///
+    /// The streams are serviced in turn from a single thread. So reads and writes must not block
+    /// indefinitely: use non-blocking streams or timeouts, since errors of kind `WouldBlock` and
+    /// `TimedOut` (as well as `Interrupted`) just mean that a stream is not ready yet. A chunk
+    /// which could not be written yet is kept and retried, so no data is lost. When neither
+    /// stream is ready, this function sleeps for a short, growing interval (up to 16ms) rather
+    /// than spinning. When one stream reaches the end, the other is flushed. Shutting down the
+    /// other direction (such as with `TcpStream::shutdown()`) is left to the caller.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if any of the underlying `read`, `write` or `flush` functions
+    /// fail with any other error, or if a write returns that it wrote zero bytes. Bytes forwarded
+    /// before the error are still counted.
+    pub fn relay(&mut self) -> io::Result<()> {
+        let (mut to_server, mut to_client) = (Pipe::new(), Pipe::new());
+        let mut idle = 0;
+
+        while to_server.is_active() || to_client.is_active() {
+            let mut progress = to_server.pump(self.client, self.server, &mut self.to_server)?;
+            progress |= to_client.pump(self.server, self.client, &mut self.to_client)?;
+
+            if progress {
+                idle = 0;
+            } else {
+                thread::sleep(Duration::from_millis(1 << idle));
+                idle = cmp::min(idle + 1, 4);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, C: 'a + io::Read + io::Write, S: 'a + io::Read + io::Write> Counter
+    for ProxyCounter<'a, C, S>
+{
+    /// Returns the total number of bytes forwarded in both directions so far.
+    fn count(&self) -> usize {
+        self.to_server + self.to_client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use testutil::Duplex;
+
+    #[test]
+    fn relay() {
+        let mut client = Duplex::new(b"GET / HTTP/1.0\r\n\r\n", 4);
+        let mut server = Duplex::new(b"HTTP/1.0 200 OK\r\n\r\nhello", 4);
+
+        let mut p = ProxyCounter::new(&mut client, &mut server);
+        p.relay().unwrap();
+        assert_eq!(p.client_to_server(), 18);
+        assert_eq!(p.server_to_client(), 24);
+        assert_eq!(p.count(), 42);
+
+        assert_eq!(&server.output[..], b"GET / HTTP/1.0\r\n\r\n");
+        assert_eq!(&client.output[..], b"HTTP/1.0 200 OK\r\n\r\nhello");
+    }
+
+    // A non-blocking stream which is only ready on every other call.
+    struct Choppy<'a> {
+        input: &'a [u8],
+        output: Vec<u8>,
+        ready: bool,
+    }
+
+    impl<'a> Choppy<'a> {
+        fn poll(&mut self) -> io::Result<()> {
+            self.ready = !self.ready;
+            if self.ready {
+                Ok(())
+            } else {
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+        }
+    }
+
+    impl<'a> Read for Choppy<'a> {
+        fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+            self.poll()?;
+            self.input.read(buffer)
+        }
+    }
+
+    impl<'a> Write for Choppy<'a> {
+        fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+            self.poll()?;
+            let size = buffer.len().min(3);
+            self.output.write(&buffer[..size])
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn would_block() {
+        let mut client = Choppy {
+            input: b"request body",
+            output: Vec::new(),
+            ready: false,
+        };
+        let mut server = Choppy {
+            input: b"response body",
+            output: Vec::new(),
+            ready: false,
+        };
+
+        let mut p = ProxyCounter::new(&mut client, &mut server);
+        p.relay().unwrap();
+        assert_eq!(p.client_to_server(), 12);
+        assert_eq!(p.server_to_client(), 13);
+
+        assert_eq!(&server.output[..], b"request body");
+        assert_eq!(&client.output[..], b"response body");
+    }
+}
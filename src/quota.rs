@@ -0,0 +1,134 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::error;
+use std::fmt;
+use std::io;
+
+use Counter;
+
+/// The error wrapped by an `io::Error` when a `QuotaWriteCounter` exceeds its quota.
+///
+/// It can be retrieved with `error.get_ref()` and `downcast_ref::<QuotaExceeded>()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    /// The quota which was exceeded.
+    pub quota: usize,
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "write quota of {} bytes exceeded", self.quota)
+    }
+}
+
+impl error::Error for QuotaExceeded {}
+
+/// Wraps any implementation of `std::io::Write`, counting the bytes written and enforcing a quota.
+///
+/// A `QuotaWriteCounter` behaves just like a `WriteCounter` until the quota is reached. A write
+/// which would cross the quota is truncated, so bytes up to the quota are still written. Once the
+/// quota is reached, any further (non-empty) write fails with an `io::Error` of kind `Other`
+/// wrapping a `QuotaExceeded`, which lets callers handle it specially.
+pub struct QuotaWriteCounter<'a, T: 'a + io::Write> {
+    writer: &'a mut T,
+    count: usize,
+    quota: usize,
+}
+
+impl<'a, T: 'a + io::Write> QuotaWriteCounter<'a, T> {
+    /// Creates a `QuotaWriteCounter` by wrapping any implementation of `std::io::Write`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(writer: &'a mut T, quota: usize) -> QuotaWriteCounter<'a, T> {
+        QuotaWriteCounter {
+            writer,
+            count: 0,
+            quota,
+        }
+    }
+
+    /// Returns the number of bytes which can still be written before the quota is reached.
+    pub fn remaining(&self) -> usize {
+        self.quota - self.count
+    }
+}
+
+impl<'a, T: 'a + io::Write> Counter for QuotaWriteCounter<'a, T> {
+    /// Returns the number of bytes written so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Write> io::Write for QuotaWriteCounter<'a, T> {
+    /// Proxies to the inner `write` function, counting the bytes written along the way.
+    ///
+    /// The buffer passed to the inner writer is truncated so the quota is not exceeded.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors. It will also return an error
+    /// wrapping a `QuotaExceeded` if the quota has already been reached.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        if !buffer.is_empty() && self.remaining() == 0 {
+            let err = QuotaExceeded { quota: self.quota };
+            return Err(io::Error::new(io::ErrorKind::Other, err));
+        }
+
+        let max = cmp::min(buffer.len(), self.remaining());
+        let size = self.writer.write(&buffer[..max])?;
+        self.count += size;
+        Ok(size)
+    }
+
+    /// Proxies to the inner `flush` function.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn quota() {
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = QuotaWriteCounter::new(&mut v, 5);
+
+        w.write_all(b"abc").unwrap();
+        let err = w.write_all(b"defg").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+
+        let inner = err
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<QuotaExceeded>());
+        assert_eq!(inner, Some(&QuotaExceeded { quota: 5 }));
+        assert_eq!(w.count(), 5);
+        assert_eq!(v, b"abcde");
+    }
+}
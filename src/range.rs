@@ -0,0 +1,101 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use std::ops::RangeInclusive;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and how many of them fall
+/// within a range of values.
+///
+/// A `RangeCountReadCounter` behaves just like a `ReadCounter`. However, it also counts the bytes
+/// whose values fall within an inclusive range, such as printable ASCII (`0x20..=0x7E`). This is
+/// useful for heuristics such as the ratio of text to binary data in a stream.
+pub struct RangeCountReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    in_range: usize,
+    range: RangeInclusive<u8>,
+}
+
+impl<'a, T: 'a + io::Read> RangeCountReadCounter<'a, T> {
+    /// Creates a `RangeCountReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, range: RangeInclusive<u8>) -> RangeCountReadCounter<'a, T> {
+        RangeCountReadCounter {
+            reader,
+            count: 0,
+            in_range: 0,
+            range,
+        }
+    }
+
+    /// Returns the number of bytes read so far whose values fall within the range.
+    pub fn in_range(&self) -> usize {
+        self.in_range
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for RangeCountReadCounter<'a, T> {
+    /// Returns the total number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for RangeCountReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+        let range = &self.range;
+        self.in_range += buffer[..size].iter().filter(|b| range.contains(b)).count();
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn printable() {
+        let data = b"Hi\x00\n there\x7f\x01";
+        let mut d = &data[..];
+        let mut r = RangeCountReadCounter::new(&mut d, 0x20..=0x7E);
+        let mut buf = [0u8; 4];
+
+        while r.read(&mut buf).unwrap() > 0 {}
+
+        assert_eq!(r.count(), data.len());
+        assert_eq!(r.in_range(), 8);
+    }
+}
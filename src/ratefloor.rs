@@ -0,0 +1,185 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::collections::VecDeque;
+use std::io;
+use std::time::{Duration, Instant};
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and reporting when the
+/// throughput stays below a floor.
+///
+/// A `RateFloorReadCounter` behaves just like a `ReadCounter`. However, after each read it
+/// computes the average rate over a moving `window` of recent reads. If that rate stays below
+/// `floor` (in bytes per second) for at least `sustain`, the callback is invoked with the current
+/// rate. It fires once per slow period: the rate must recover before it can fire again.
+///
+/// The rate is only evaluated when a read completes. So a stream which stalls completely is
+/// reported on the read which ends the stall; use an `IdleReadCounter` to detect stalls directly.
+pub struct RateFloorReadCounter<'a, T: 'a + io::Read, F: FnMut(f64)> {
+    reader: &'a mut T,
+    count: usize,
+    floor: f64,
+    window: Duration,
+    sustain: Duration,
+    callback: F,
+    start: Instant,
+    samples: VecDeque<(Instant, usize)>,
+    windowed: usize,
+    below_since: Option<Instant>,
+    fired: bool,
+}
+
+impl<'a, T: 'a + io::Read, F: FnMut(f64)> RateFloorReadCounter<'a, T, F> {
+    /// Creates a `RateFloorReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(
+        reader: &'a mut T,
+        floor: f64,
+        window: Duration,
+        sustain: Duration,
+        callback: F,
+    ) -> RateFloorReadCounter<'a, T, F> {
+        RateFloorReadCounter {
+            reader,
+            count: 0,
+            floor,
+            window,
+            sustain,
+            callback,
+            start: Instant::now(),
+            samples: VecDeque::new(),
+            windowed: 0,
+            below_since: None,
+            fired: false,
+        }
+    }
+
+    /// Returns the average rate, in bytes per second, over the moving window as of the last read.
+    ///
+    /// Until the window has elapsed once, the rate is averaged over the time since creation.
+    pub fn rate(&self) -> f64 {
+        self.rate_at(self.samples.back().map_or(self.start, |s| s.0))
+    }
+
+    /// Returns whether the rate is currently below the floor.
+    pub fn is_below_floor(&self) -> bool {
+        self.below_since.is_some()
+    }
+
+    fn rate_at(&self, now: Instant) -> f64 {
+        let span = now.duration_since(self.start).min(self.window);
+        match span.as_secs_f64() {
+            secs if secs > 0.0 => self.windowed as f64 / secs,
+            _ => f64::INFINITY,
+        }
+    }
+
+    fn update(&mut self, now: Instant, size: usize) {
+        self.samples.push_back((now, size));
+        self.windowed += size;
+
+        while let Some(&(at, size)) = self.samples.front() {
+            if now.duration_since(at) <= self.window {
+                break;
+            }
+
+            self.windowed -= size;
+            self.samples.pop_front();
+        }
+
+        let rate = self.rate_at(now);
+        if rate >= self.floor {
+            self.below_since = None;
+            self.fired = false;
+            return;
+        }
+
+        let since = *self.below_since.get_or_insert(now);
+        if !self.fired && now.duration_since(since) >= self.sustain {
+            self.fired = true;
+            (self.callback)(rate);
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read, F: FnMut(f64)> Counter for RateFloorReadCounter<'a, T, F> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read, F: FnMut(f64)> io::Read for RateFloorReadCounter<'a, T, F> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+        self.update(Instant::now(), size);
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floor() {
+        let data = [0u8; 100];
+        let mut d = &data[..];
+        let mut fired = Vec::new();
+
+        {
+            let window = Duration::from_millis(50);
+            let sustain = Duration::from_millis(20);
+            let mut r =
+                RateFloorReadCounter::new(&mut d, 1000.0, window, sustain, |rate| fired.push(rate));
+            let start = r.start;
+            let at = |ms| start + Duration::from_millis(ms);
+
+            for _ in 0..3 {
+                r.update(at(1), 1);
+            }
+            assert!(!r.is_below_floor());
+
+            // The first reads leave the window, so the rate drops to 20 bytes per second.
+            r.update(at(61), 1);
+            assert!(r.is_below_floor());
+            assert_eq!(r.rate(), 20.0);
+
+            r.update(at(86), 1);
+            r.update(at(111), 1);
+            assert_eq!(r.rate(), 60.0);
+        }
+
+        assert_eq!(fired, vec![40.0]);
+    }
+}
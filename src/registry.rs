@@ -0,0 +1,89 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Aggregates the counts of any number of counters.
+///
+/// A `Registry` holds atomic totals which are updated by every counter registered with it (using
+/// `ReadCounter::register()` or `WriteCounter::register()`). This allows querying the total bytes
+/// across every stream in an application without passing counters around.
+///
+/// Only bytes counted after a counter is registered are included in the totals. A counter is
+/// deregistered when it is dropped or registered with another registry. The totals are kept after
+/// deregistration; only `registered()` decreases.
+#[derive(Debug, Default)]
+pub struct Registry {
+    read: AtomicUsize,
+    written: AtomicUsize,
+    registered: AtomicUsize,
+}
+
+impl Registry {
+    /// Creates a new `Registry`, ready to be shared between counters.
+    pub fn new() -> Arc<Registry> {
+        Arc::new(Registry::default())
+    }
+
+    /// Returns the total number of bytes read by all registered counters.
+    pub fn bytes_read(&self) -> usize {
+        self.read.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total number of bytes written by all registered counters.
+    pub fn bytes_written(&self) -> usize {
+        self.written.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total number of bytes read or written by all registered counters.
+    ///
+    /// The total saturates at `usize::max_value()` rather than overflowing.
+    pub fn total(&self) -> usize {
+        self.bytes_read().saturating_add(self.bytes_written())
+    }
+
+    /// Returns the number of counters currently registered.
+    pub fn registered(&self) -> usize {
+        self.registered.load(Ordering::Relaxed)
+    }
+}
+
+// Held by a counter while it is registered with a `Registry`.
+pub(crate) struct Registration(Arc<Registry>);
+
+impl Registration {
+    pub(crate) fn new(registry: &Arc<Registry>) -> Registration {
+        registry.registered.fetch_add(1, Ordering::Relaxed);
+        Registration(registry.clone())
+    }
+
+    pub(crate) fn read(&self, size: usize) {
+        self.0.read.fetch_add(size, Ordering::Relaxed);
+    }
+
+    pub(crate) fn written(&self, size: usize) {
+        self.0.written.fetch_add(size, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.0.registered.fetch_sub(1, Ordering::Relaxed);
+    }
+}
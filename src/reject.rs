@@ -0,0 +1,139 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and rejecting streams
+/// which contain forbidden bytes.
+///
+/// A `RejectReadCounter` scans every read for any of a set of forbidden bytes (such as null bytes
+/// in a text protocol). If one is found, the read fails with an error of kind `InvalidData` and so
+/// does every later read: the stream is rejected as a whole. Since each read is scanned in full,
+/// forbidden bytes are caught regardless of buffer boundaries.
+pub struct RejectReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    forbidden: [bool; 256],
+    rejected: Option<usize>,
+}
+
+fn rejected() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream contained a forbidden byte",
+    )
+}
+
+impl<'a, T: 'a + io::Read> RejectReadCounter<'a, T> {
+    /// Creates a `RejectReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, forbidden: &[u8]) -> RejectReadCounter<'a, T> {
+        let mut set = [false; 256];
+        for byte in forbidden {
+            set[usize::from(*byte)] = true;
+        }
+
+        RejectReadCounter {
+            reader,
+            count: 0,
+            forbidden: set,
+            rejected: None,
+        }
+    }
+
+    /// Returns the offset within the stream of the first forbidden byte, if one has been found.
+    pub fn rejected_at(&self) -> Option<usize> {
+        self.rejected
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for RejectReadCounter<'a, T> {
+    /// Returns the number of bytes read so far, excluding the read which was rejected.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for RejectReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors. It will also return an error
+    /// of kind `InvalidData` if the bytes read contain a forbidden byte, or if a previous read did.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        if self.rejected.is_some() {
+            return Err(rejected());
+        }
+
+        let size = self.reader.read(buffer)?;
+
+        let forbidden = &self.forbidden;
+        if let Some(index) = buffer[..size]
+            .iter()
+            .position(|b| forbidden[usize::from(*b)])
+        {
+            self.rejected = Some(self.count + index);
+            return Err(rejected());
+        }
+
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn accepted() {
+        let mut d = &b"hello"[..];
+        let mut r = RejectReadCounter::new(&mut d, b"\0");
+        let mut out = Vec::new();
+
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(r.rejected_at(), None);
+        assert_eq!(r.count(), 5);
+    }
+
+    #[test]
+    fn rejected() {
+        let mut d = &b"hel\0lo"[..];
+        let mut r = RejectReadCounter::new(&mut d, b"\0\xFF");
+        let mut buf = [0u8; 2];
+
+        assert_eq!(r.read(&mut buf).unwrap(), 2);
+        let err = r.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(r.rejected_at(), Some(3));
+
+        assert!(r.read(&mut buf).is_err());
+        assert_eq!(r.count(), 2);
+    }
+}
@@ -0,0 +1,165 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+// The multiplier of the polynomial rolling hash.
+const BASE: u64 = 0x0100_0000_01B3;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and maintaining a rolling
+/// hash of the most recent bytes.
+///
+/// A `RollingHashReadCounter` behaves just like a `ReadCounter`. However, it also maintains a
+/// Rabin-Karp style polynomial hash over a sliding window of the last `window` bytes read. Since
+/// the hash depends only on the window contents, it is the same however the stream is split into
+/// reads. This enables content-defined chunking (such as for deduplication) in a single pass:
+/// wrap a `BufReader`, read one byte at a time and cut a chunk whenever `boundary()` is true.
+pub struct RollingHashReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    window: Vec<u8>,
+    pos: usize,
+    hash: u64,
+    out: u64,
+}
+
+impl<'a, T: 'a + io::Read> RollingHashReadCounter<'a, T> {
+    /// Creates a `RollingHashReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    ///
+    /// # Panics
+    ///
+    /// If `window` is zero.
+    #[must_use]
+    pub fn new(reader: &'a mut T, window: usize) -> RollingHashReadCounter<'a, T> {
+        assert!(window > 0, "window must be nonzero");
+
+        // The factor by which the byte leaving the window was multiplied: BASE ^ (window - 1).
+        let out = (1..window).fold(1u64, |acc, _| acc.wrapping_mul(BASE));
+
+        RollingHashReadCounter {
+            reader,
+            count: 0,
+            window: vec![0; window],
+            pos: 0,
+            hash: 0,
+            out,
+        }
+    }
+
+    /// Returns the rolling hash of the current window.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns whether the current position is a chunk boundary.
+    ///
+    /// This is true when all of the bits of the hash selected by `mask` are zero. With `k` bits
+    /// set in `mask`, boundaries occur on average every `2^k` bytes. It is never true before a
+    /// full window has been read.
+    pub fn boundary(&self, mask: u64) -> bool {
+        self.count >= self.window.len() && self.hash & mask == 0
+    }
+
+    fn update(&mut self, byte: u8) {
+        let size = self.window.len();
+
+        // Bytes are offset by one so that runs of zeros still contribute to the hash.
+        let incoming = u64::from(byte) + 1;
+        if self.count >= size {
+            let outgoing = u64::from(self.window[self.pos]) + 1;
+            self.hash = self.hash.wrapping_sub(outgoing.wrapping_mul(self.out));
+        }
+
+        self.hash = self.hash.wrapping_mul(BASE).wrapping_add(incoming);
+        self.window[self.pos] = byte;
+        self.pos = (self.pos + 1) % size;
+        self.count += 1;
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for RollingHashReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for RollingHashReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting and hashing the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+
+        for byte in &buffer[..size] {
+            self.update(*byte);
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn boundaries(data: &[u8], chunk: usize) -> (Vec<usize>, u64) {
+        let mut d = data;
+        let mut r = RollingHashReadCounter::new(&mut d, 16);
+        let mut buf = vec![0u8; chunk];
+        let mut found = Vec::new();
+
+        while r.read(&mut buf).unwrap() > 0 {
+            if r.boundary(0x1F) {
+                found.push(r.count());
+            }
+        }
+
+        (found, r.hash())
+    }
+
+    #[test]
+    fn deterministic() {
+        let data: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+
+        let (single, hash) = boundaries(&data, 1);
+        assert!(!single.is_empty());
+        assert!(single.iter().all(|b| *b >= 16));
+        assert_eq!(boundaries(&data, 1), (single, hash));
+
+        let (_, chunked) = boundaries(&data, 100);
+        assert_eq!(chunked, hash);
+
+        let mut tail = &data[data.len() - 16..];
+        let mut r = RollingHashReadCounter::new(&mut tail, 16);
+        r.read_to_end(&mut Vec::new()).unwrap();
+        assert_eq!(r.hash(), hash);
+    }
+}
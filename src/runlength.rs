@@ -0,0 +1,141 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and gathering run-length
+/// statistics about them.
+///
+/// A `RunLengthStatsReadCounter` behaves just like a `ReadCounter`. However, it also tracks runs of
+/// a single repeated byte value, even when they span reads. The longest run and the number of
+/// transitions between runs give a cheap, one-pass hint of how compressible the stream is.
+pub struct RunLengthStatsReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    current: Option<u8>,
+    run: usize,
+    longest: Option<(u8, usize)>,
+    transitions: usize,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for RunLengthStatsReadCounter<'a, T> {
+    /// Creates a `RunLengthStatsReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> RunLengthStatsReadCounter<'a, T> {
+        RunLengthStatsReadCounter {
+            reader: value,
+            count: 0,
+            current: None,
+            run: 0,
+            longest: None,
+            transitions: 0,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> RunLengthStatsReadCounter<'a, T> {
+    /// Returns the length of the longest run of a single byte value read so far.
+    pub fn longest_run(&self) -> usize {
+        self.longest.map_or(0, |(_, len)| len)
+    }
+
+    /// Returns the byte value of the longest run read so far, if any.
+    ///
+    /// If several runs share the longest length, this is the value of the first of them.
+    pub fn longest_run_byte(&self) -> Option<u8> {
+        self.longest.map(|(byte, _)| byte)
+    }
+
+    /// Returns the number of transitions from one byte value to a different one.
+    ///
+    /// The number of runs read so far is one more than this (unless nothing has been read).
+    pub fn transitions(&self) -> usize {
+        self.transitions
+    }
+
+    fn update(&mut self, byte: u8) {
+        match self.current {
+            Some(current) if current == byte => self.run += 1,
+            Some(_) => {
+                self.transitions += 1;
+                self.run = 1;
+            }
+            None => self.run = 1,
+        }
+
+        self.current = Some(byte);
+        if self.run > self.longest_run() {
+            self.longest = Some((byte, self.run));
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for RunLengthStatsReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for RunLengthStatsReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+
+        for byte in &buffer[..size] {
+            self.update(*byte);
+        }
+
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn runs() {
+        let data = b"aaabbbbbbcaaaaa";
+        let mut d = &data[..];
+        let mut r = RunLengthStatsReadCounter::from(&mut d);
+        let mut buf = [0u8; 4];
+
+        assert_eq!(r.longest_run_byte(), None);
+        while r.read(&mut buf).unwrap() > 0 {}
+
+        assert_eq!(r.longest_run(), 6);
+        assert_eq!(r.longest_run_byte(), Some(b'b'));
+        assert_eq!(r.transitions(), 3);
+        assert_eq!(r.count(), data.len());
+    }
+}
@@ -0,0 +1,173 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and checking them against
+/// a schema of section lengths.
+///
+/// A `SectionedReadCounter` is constructed with the expected lengths of consecutive sections of
+/// the stream (such as a header, a body and a footer). Section boundaries are derived from the
+/// running count, so reads may span sections freely. When the stream ends, `read` fails if the
+/// stream was shorter or longer than the schema.
+pub struct SectionedReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    sections: Vec<usize>,
+}
+
+impl<'a, T: 'a + io::Read> SectionedReadCounter<'a, T> {
+    /// Creates a `SectionedReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, sections: Vec<usize>) -> SectionedReadCounter<'a, T> {
+        SectionedReadCounter {
+            reader,
+            count: 0,
+            sections,
+        }
+    }
+
+    /// Returns the index of the section the next byte belongs to.
+    ///
+    /// Empty sections are skipped. Once every section is complete, this is the number of sections.
+    pub fn current_section(&self) -> usize {
+        let mut end = 0;
+
+        for (index, len) in self.sections.iter().enumerate() {
+            end += len;
+            if self.count < end {
+                return index;
+            }
+        }
+
+        self.sections.len()
+    }
+
+    /// Returns the number of bytes read so far within each section.
+    pub fn section_counts(&self) -> Vec<usize> {
+        let mut start = 0;
+
+        self.sections
+            .iter()
+            .map(|len| {
+                let read = cmp::min(self.count.saturating_sub(start), *len);
+                start += len;
+                read
+            })
+            .collect()
+    }
+
+    fn check_end(&self) -> io::Result<()> {
+        let total: usize = self.sections.iter().sum();
+
+        if self.count < total {
+            let msg = format!("stream ended in section {}", self.current_section());
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, msg));
+        }
+
+        if self.count > total {
+            let msg = format!(
+                "stream overran the last section by {} bytes",
+                self.count - total
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for SectionedReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for SectionedReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors. At the end of the stream, it
+    /// will also return an error of kind `UnexpectedEof` if a section was cut short, or of kind
+    /// `InvalidData` if the stream continued past the last section.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+
+        if size == 0 && !buffer.is_empty() {
+            self.check_end()?;
+        }
+
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn matching() {
+        let data = [0u8; 10];
+        let mut d = &data[..];
+        let mut r = SectionedReadCounter::new(&mut d, vec![4, 0, 5, 1]);
+        let mut buf = [0u8; 3];
+
+        assert_eq!(r.current_section(), 0);
+        r.read_exact(&mut buf).unwrap();
+        r.read_exact(&mut buf).unwrap();
+        assert_eq!(r.current_section(), 2);
+        assert_eq!(r.section_counts(), vec![4, 0, 2, 0]);
+
+        let mut rest = Vec::new();
+        r.read_to_end(&mut rest).unwrap();
+        assert_eq!(r.current_section(), 4);
+        assert_eq!(r.section_counts(), vec![4, 0, 5, 1]);
+    }
+
+    #[test]
+    fn truncated() {
+        let data = [0u8; 6];
+        let mut d = &data[..];
+        let mut r = SectionedReadCounter::new(&mut d, vec![4, 5]);
+        let mut rest = Vec::new();
+
+        let err = r.read_to_end(&mut rest).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(r.section_counts(), vec![4, 2]);
+        assert_eq!(r.count(), 6);
+
+        let mut d = &data[..];
+        let mut r = SectionedReadCounter::new(&mut d, vec![2, 3]);
+        let err = r.read_to_end(&mut rest).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,260 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and copying them into a
+/// sequence of fixed-size segments.
+///
+/// A `SegmentingReadCounter` passes data through just like a `ReadCounter`. However, it also
+/// writes every byte read into the current segment's sink. After `segment_size` bytes, the sink
+/// is flushed and dropped, and the next byte opens a new one by calling the factory with the index
+/// of the new segment (starting from zero). This splits a stream into files, like log rotation.
+///
+/// Reads are truncated so they never cross a segment boundary. If the factory or a sink fails, the
+/// bytes are still returned from `read`, and the error is returned by the next call instead. The
+/// affected segment may then be incomplete.
+pub struct SegmentingReadCounter<
+    'a,
+    T: 'a + io::Read,
+    F: FnMut(usize) -> io::Result<Box<dyn io::Write>>,
+> {
+    reader: &'a mut T,
+    count: usize,
+    segment_size: usize,
+    factory: F,
+    sink: Option<Box<dyn io::Write>>,
+    segments: usize,
+    current: usize,
+    error: Option<io::Error>,
+}
+
+impl<'a, T: 'a + io::Read, F: FnMut(usize) -> io::Result<Box<dyn io::Write>>>
+    SegmentingReadCounter<'a, T, F>
+{
+    /// Creates a `SegmentingReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    ///
+    /// # Panics
+    ///
+    /// If `segment_size` is zero.
+    #[must_use]
+    pub fn new(
+        reader: &'a mut T,
+        segment_size: usize,
+        factory: F,
+    ) -> SegmentingReadCounter<'a, T, F> {
+        assert!(segment_size > 0, "segment_size must be nonzero");
+
+        SegmentingReadCounter {
+            reader,
+            count: 0,
+            segment_size,
+            factory,
+            sink: None,
+            segments: 0,
+            current: 0,
+            error: None,
+        }
+    }
+
+    /// Returns the number of segments opened so far.
+    pub fn segments(&self) -> usize {
+        self.segments
+    }
+
+    /// Returns the number of bytes written into the current segment.
+    pub fn current_segment_len(&self) -> usize {
+        self.current
+    }
+
+    // Copies bytes which were just read into the current segment, closing it if it is full.
+    fn copy(&mut self, data: &[u8]) -> io::Result<()> {
+        let result = self.write(data);
+
+        self.current += data.len();
+        if self.current < self.segment_size {
+            return result;
+        }
+
+        self.current = 0;
+        let closed = self.close();
+        result.and(closed)
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.sink.is_none() {
+            self.sink = Some((self.factory)(self.segments)?);
+            self.segments += 1;
+        }
+
+        match self.sink {
+            Some(ref mut sink) => sink.write_all(data),
+            None => Ok(()),
+        }
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        if let Some(mut sink) = self.sink.take() {
+            sink.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, T: 'a + io::Read, F: FnMut(usize) -> io::Result<Box<dyn io::Write>>> Counter
+    for SegmentingReadCounter<'a, T, F>
+{
+    /// Returns the total number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read, F: FnMut(usize) -> io::Result<Box<dyn io::Write>>> io::Read
+    for SegmentingReadCounter<'a, T, F>
+{
+    /// Proxies to the inner `read` function, copying the bytes read into the current segment and
+    /// counting them along the way.
+    ///
+    /// At the end of the stream, the last segment is flushed.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors. It will also error if the
+    /// factory, or a sink's `write` or `flush` function, errored during the previous call. Nothing
+    /// is read in either case. An error flushing the last segment at the end of the stream is
+    /// returned directly.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        let limit = cmp::min(buffer.len(), self.segment_size - self.current);
+        let size = self.reader.read(&mut buffer[..limit])?;
+
+        if size == 0 {
+            if !buffer.is_empty() {
+                self.close()?;
+            }
+
+            return Ok(0);
+        }
+
+        self.count += size;
+        if let Err(e) = self.copy(&buffer[..size]) {
+            self.error = Some(e);
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::io::{Read, Write};
+    use std::rc::Rc;
+
+    struct Sink(Rc<RefCell<Vec<Vec<u8>>>>, usize);
+
+    impl Write for Sink {
+        fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+            self.0.borrow_mut()[self.1].extend_from_slice(buffer);
+            Ok(buffer.len())
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn segments() {
+        let data: Vec<u8> = (0..25).collect();
+        let mut d = &data[..];
+        let files = Rc::new(RefCell::new(Vec::new()));
+        let f = files.clone();
+
+        let mut r = SegmentingReadCounter::new(&mut d, 10, move |index| {
+            f.borrow_mut().push(Vec::new());
+            Ok(Box::new(Sink(f.clone(), index)) as Box<dyn Write>)
+        });
+
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data);
+        assert_eq!(r.segments(), 3);
+        assert_eq!(r.current_segment_len(), 5);
+        assert_eq!(r.count(), 25);
+
+        let files = files.borrow();
+        let sizes: Vec<usize> = files.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![10, 10, 5]);
+        assert_eq!(files.concat(), data);
+    }
+
+    #[test]
+    fn failed_sink() {
+        let data: Vec<u8> = (0..25).collect();
+        let mut d = &data[..];
+        let files = Rc::new(RefCell::new(Vec::new()));
+        let f = files.clone();
+
+        let mut r = SegmentingReadCounter::new(&mut d, 10, move |index| {
+            if index == 1 && f.borrow().len() == 1 {
+                f.borrow_mut().push(Vec::new());
+                return Err(io::Error::new(io::ErrorKind::Other, "cannot open"));
+            }
+
+            f.borrow_mut().push(Vec::new());
+            let slot = f.borrow().len() - 1;
+            Ok(Box::new(Sink(f.clone(), slot)) as Box<dyn Write>)
+        });
+
+        let mut buffer = [0u8; 10];
+        let mut out = Vec::new();
+        assert_eq!(r.read(&mut buffer).unwrap(), 10);
+        out.extend_from_slice(&buffer);
+
+        // The bytes are returned even though their segment cannot be opened.
+        assert_eq!(r.read(&mut buffer[..3]).unwrap(), 3);
+        out.extend_from_slice(&buffer[..3]);
+        let err = r.read(&mut buffer).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data);
+        assert_eq!(r.count(), 25);
+
+        let files = files.borrow();
+        let sizes: Vec<usize> = files.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![10, 0, 7, 5]);
+    }
+}
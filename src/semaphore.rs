@@ -0,0 +1,266 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+use Counter;
+
+/// A simple counting semaphore, used by `SemaphoreReadCounter`.
+///
+/// Each permit allows one byte to be read. Downstream consumers release permits as they finish
+/// with the data, which couples the read rate to the rate of consumption.
+#[derive(Debug, Default)]
+pub struct Semaphore {
+    permits: Mutex<usize>,
+    available: Condvar,
+}
+
+impl Semaphore {
+    /// Creates a `Semaphore` with the given number of available permits.
+    #[must_use]
+    pub fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            permits: Mutex::new(permits),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Returns the number of permits currently available.
+    pub fn available(&self) -> usize {
+        *self.lock()
+    }
+
+    /// Blocks until `n` permits are available, then takes them.
+    pub fn acquire(&self, n: usize) {
+        let mut permits = self.lock();
+        while *permits < n {
+            permits = self
+                .available
+                .wait(permits)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+
+        *permits -= n;
+    }
+
+    /// Returns `n` permits, waking any blocked reader.
+    pub fn release(&self, n: usize) {
+        *self.lock() += n;
+        self.available.notify_all();
+    }
+
+    // Blocks until at least one permit is available, then takes as many as possible up to `max`.
+    fn acquire_up_to(&self, max: usize) -> usize {
+        let mut permits = self.lock();
+        while *permits == 0 {
+            permits = self
+                .available
+                .wait(permits)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+
+        let taken = cmp::min(*permits, max);
+        *permits -= taken;
+        taken
+    }
+
+    // Takes as many permits as possible up to `max`, without blocking.
+    fn try_acquire_up_to(&self, max: usize) -> usize {
+        let mut permits = self.lock();
+        let taken = cmp::min(*permits, max);
+        *permits -= taken;
+        taken
+    }
+
+    fn lock(&self) -> MutexGuard<'_, usize> {
+        self.permits.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and applying backpressure
+/// through a `Semaphore`.
+///
+/// A `SemaphoreReadCounter` takes one permit from a shared `Semaphore` for every byte read. If no
+/// permits are available, `read` blocks until another thread releases some. Reads are truncated
+/// to the number of permits available, and permits for bytes which were not read are returned.
+/// This bounds the amount of data in flight in a pipeline.
+///
+/// Detecting the end of the stream does not need a permit: when none are available, a single
+/// byte is read ahead before blocking, so a `Semaphore` holding exactly as many permits as the
+/// stream has bytes does not deadlock `read_to_end()`. The byte read ahead is held until a permit
+/// is available and is only counted once returned.
+pub struct SemaphoreReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    semaphore: Arc<Semaphore>,
+    peeked: Option<u8>,
+}
+
+impl<'a, T: 'a + io::Read> SemaphoreReadCounter<'a, T> {
+    /// Creates a `SemaphoreReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, semaphore: Arc<Semaphore>) -> SemaphoreReadCounter<'a, T> {
+        SemaphoreReadCounter {
+            reader,
+            count: 0,
+            semaphore,
+            peeked: None,
+        }
+    }
+
+    /// Returns the semaphore which permits reads.
+    pub fn semaphore(&self) -> &Arc<Semaphore> {
+        &self.semaphore
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for SemaphoreReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for SemaphoreReadCounter<'a, T> {
+    /// Waits for permits, then proxies to the inner `read` function, counting the bytes read along
+    /// the way. If the stream has ended, this returns zero without waiting.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors. The permits taken for the
+    /// read are returned.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        let mut permits = self.semaphore.try_acquire_up_to(buffer.len());
+        if permits == 0 {
+            if self.peeked.is_none() {
+                let mut byte = [0u8];
+                if self.reader.read(&mut byte)? == 0 {
+                    return Ok(0);
+                }
+
+                self.peeked = Some(byte[0]);
+            }
+
+            permits = self.semaphore.acquire_up_to(buffer.len());
+        }
+
+        let offset = match self.peeked.take() {
+            Some(byte) => {
+                buffer[0] = byte;
+                1
+            }
+            None => 0,
+        };
+
+        let size = match self.reader.read(&mut buffer[offset..permits]) {
+            Ok(size) => size + offset,
+            Err(e) => {
+                if offset > 0 {
+                    self.peeked = Some(buffer[0]);
+                }
+
+                self.semaphore.release(permits);
+                return Err(e);
+            }
+        };
+
+        self.semaphore.release(permits - size);
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn backpressure() {
+        let semaphore = Arc::new(Semaphore::new(3));
+        let data = [0u8; 5];
+        let mut d = &data[..];
+        let mut r = SemaphoreReadCounter::new(&mut d, semaphore.clone());
+        let mut buf = [0u8; 10];
+
+        assert_eq!(r.read(&mut buf).unwrap(), 3);
+        assert_eq!(semaphore.available(), 0);
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            semaphore.release(4);
+        });
+
+        assert_eq!(r.read(&mut buf).unwrap(), 2);
+        releaser.join().unwrap();
+        assert_eq!(r.semaphore().available(), 2);
+        assert_eq!(r.count(), 5);
+    }
+
+    #[test]
+    fn exact_budget() {
+        let semaphore = Arc::new(Semaphore::new(5));
+        let data = *b"abcde";
+        let mut d = &data[..];
+        let mut r = SemaphoreReadCounter::new(&mut d, semaphore.clone());
+
+        let mut out = Vec::new();
+        assert_eq!(r.read_to_end(&mut out).unwrap(), 5);
+        assert_eq!(&out[..], b"abcde");
+        assert_eq!(semaphore.available(), 0);
+        assert_eq!(r.count(), 5);
+    }
+
+    #[test]
+    fn read_ahead() {
+        let semaphore = Arc::new(Semaphore::new(2));
+        let data = *b"abc";
+        let mut d = &data[..];
+        let mut r = SemaphoreReadCounter::new(&mut d, semaphore.clone());
+        let mut buf = [0u8; 10];
+
+        assert_eq!(r.read(&mut buf).unwrap(), 2);
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            semaphore.release(2);
+        });
+
+        assert_eq!(r.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], b'c');
+        releaser.join().unwrap();
+        assert_eq!(r.read(&mut buf).unwrap(), 0);
+        assert_eq!(r.semaphore().available(), 1);
+        assert_eq!(r.count(), 3);
+    }
+}
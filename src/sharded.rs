@@ -0,0 +1,204 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use Counter;
+
+// Keeps each shard on its own cache line, so writers on different shards do not contend.
+#[derive(Debug, Default)]
+#[repr(align(64))]
+struct Shard(AtomicU64);
+
+/// A set of atomic byte counts, used by `ShardedWriteCounter`.
+///
+/// Spreading a shared count across several shards avoids the cache-line contention of a single
+/// atomic when many threads write at once. The shards are only summed when the total is needed.
+#[derive(Debug)]
+pub struct Shards {
+    shards: Vec<Shard>,
+}
+
+impl Shards {
+    /// Creates a set of `shards` atomic counts, all starting at zero.
+    ///
+    /// # Panics
+    ///
+    /// If `shards` is zero.
+    #[must_use]
+    pub fn new(shards: usize) -> Shards {
+        assert!(shards > 0, "shards must be nonzero");
+
+        Shards {
+            shards: (0..shards).map(|_| Shard::default()).collect(),
+        }
+    }
+
+    /// Returns the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the sum of all shards.
+    ///
+    /// Writes which happen concurrently with this call may or may not be included.
+    pub fn total(&self) -> u64 {
+        self.shards
+            .iter()
+            .map(|shard| shard.0.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+/// Wraps any implementation of `std::io::Write`, counting the bytes written into one shard of a
+/// shared `Shards`.
+///
+/// A `ShardedWriteCounter` behaves just like a `WriteCounter`. However, it also adds the bytes
+/// written to a single shard of a `Shards` which is shared with other counters, typically one per
+/// thread. By default, the shard is chosen from the identity of the thread which creates the
+/// counter; `with_shard()` selects one explicitly.
+pub struct ShardedWriteCounter<'a, T: 'a + io::Write> {
+    writer: &'a mut T,
+    count: usize,
+    shards: Arc<Shards>,
+    shard: usize,
+}
+
+impl<'a, T: 'a + io::Write> ShardedWriteCounter<'a, T> {
+    /// Creates a `ShardedWriteCounter` by wrapping any implementation of `std::io::Write`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(writer: &'a mut T, shards: Arc<Shards>) -> ShardedWriteCounter<'a, T> {
+        let mut hasher = DefaultHasher::new();
+        thread::current().id().hash(&mut hasher);
+        let shard = (hasher.finish() % shards.shard_count() as u64) as usize;
+
+        ShardedWriteCounter {
+            writer,
+            count: 0,
+            shards,
+            shard,
+        }
+    }
+
+    /// Counts into the shard at `index` instead of the one chosen for the current thread.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is not less than the number of shards.
+    #[must_use]
+    pub fn with_shard(mut self, index: usize) -> ShardedWriteCounter<'a, T> {
+        assert!(
+            index < self.shards.shard_count(),
+            "shard index out of range"
+        );
+        self.shard = index;
+        self
+    }
+
+    /// Returns the index of the shard this instance counts into.
+    pub fn shard(&self) -> usize {
+        self.shard
+    }
+
+    /// Returns the sum of all shards, including those of other counters.
+    pub fn total(&self) -> u64 {
+        self.shards.total()
+    }
+}
+
+impl<'a, T: 'a + io::Write> Counter for ShardedWriteCounter<'a, T> {
+    /// Returns the number of bytes written through this instance so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Write> io::Write for ShardedWriteCounter<'a, T> {
+    /// Proxies to the inner `write` function, counting the bytes written along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are written across all calls to `write`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        let size = self.writer.write(buffer)?;
+        self.shards.shards[self.shard]
+            .0
+            .fetch_add(size as u64, Ordering::Relaxed);
+        self.count += size;
+        Ok(size)
+    }
+
+    /// Proxies to the inner `flush` function.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn threads() {
+        let shards = Arc::new(Shards::new(4));
+
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let shards = shards.clone();
+                thread::spawn(move || {
+                    let mut v: Vec<u8> = Vec::new();
+                    let mut w = ShardedWriteCounter::new(&mut v, shards);
+                    assert!(w.shard() < 4);
+
+                    for _ in 0..100 {
+                        w.write_all(&[0u8; 10][..i + 1]).unwrap();
+                    }
+                    w.count()
+                })
+            })
+            .collect();
+
+        let written: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+        assert_eq!(written, 100 * (1..=8).sum::<usize>());
+        assert_eq!(shards.total(), written as u64);
+    }
+
+    #[test]
+    fn with_shard() {
+        let shards = Arc::new(Shards::new(2));
+        let mut v: Vec<u8> = Vec::new();
+        let mut w = ShardedWriteCounter::new(&mut v, shards.clone()).with_shard(1);
+
+        w.write_all(b"abc").unwrap();
+        assert_eq!(w.total(), 3);
+        assert_eq!(shards.shards[1].0.load(Ordering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,166 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use Counter;
+
+/// Reads from an in-memory byte slice and counts the bytes.
+///
+/// A `SliceReadCounter` is intended for regions such as memory-mapped files, where lookahead is
+/// cheap. It exposes the current absolute offset within the slice and allows peeking at upcoming
+/// bytes without consuming them. Seeking moves the position but does not change the count.
+pub struct SliceReadCounter<'a> {
+    slice: &'a [u8],
+    position: usize,
+    count: usize,
+}
+
+impl<'a> From<&'a [u8]> for SliceReadCounter<'a> {
+    /// Creates a `SliceReadCounter` positioned at the start of the slice.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the slice.
+    fn from(value: &'a [u8]) -> SliceReadCounter<'a> {
+        SliceReadCounter {
+            slice: value,
+            position: 0,
+            count: 0,
+        }
+    }
+}
+
+impl<'a> SliceReadCounter<'a> {
+    /// Returns up to `n` bytes following the current position without consuming them.
+    ///
+    /// Fewer than `n` bytes are returned if the end of the slice is near. Neither the count nor
+    /// the position is changed.
+    pub fn peek(&self, n: usize) -> &'a [u8] {
+        let rest = &self.slice[self.position..];
+        &rest[..cmp::min(n, rest.len())]
+    }
+
+    /// Returns the current absolute offset within the slice.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl<'a> Counter for SliceReadCounter<'a> {
+    /// Returns the total number of bytes read.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a> io::Read for SliceReadCounter<'a> {
+    /// Copies bytes from the current position in the slice and counts them.
+    ///
+    /// # Panics
+    ///
+    /// If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function never errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let data = self.peek(buffer.len());
+        buffer[..data.len()].copy_from_slice(data);
+        self.position += data.len();
+        self.count += data.len();
+        Ok(data.len())
+    }
+}
+
+impl<'a> io::BufRead for SliceReadCounter<'a> {
+    /// Returns the remainder of the slice without consuming it.
+    fn fill_buf(&mut self) -> Result<&[u8], io::Error> {
+        Ok(&self.slice[self.position..])
+    }
+
+    /// Consumes and counts `amt` bytes, clamped to the end of the slice.
+    fn consume(&mut self, amt: usize) {
+        let amt = cmp::min(amt, self.slice.len() - self.position);
+        self.position += amt;
+        self.count += amt;
+    }
+}
+
+impl<'a> io::Seek for SliceReadCounter<'a> {
+    /// Moves the position within the slice without changing the count.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error of kind `InvalidInput` if the new position would be
+    /// negative or beyond the end of the slice.
+    fn seek(&mut self, pos: io::SeekFrom) -> Result<u64, io::Error> {
+        let (base, offset) = match pos {
+            io::SeekFrom::Start(offset) => (0, offset as i64),
+            io::SeekFrom::End(offset) => (self.slice.len() as i64, offset),
+            io::SeekFrom::Current(offset) => (self.position as i64, offset),
+        };
+
+        match base.checked_add(offset) {
+            Some(position) if position >= 0 && position as u64 <= self.slice.len() as u64 => {
+                self.position = position as usize;
+                Ok(position as u64)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek outside of the slice",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Seek, SeekFrom};
+
+    #[test]
+    fn peek() {
+        let mut counter = SliceReadCounter::from(&b"abcdefgh"[..]);
+
+        assert_eq!(counter.peek(3), b"abc");
+        assert_eq!(counter.position(), 0);
+        assert_eq!(counter.count(), 0);
+
+        let mut buffer = [0u8; 5];
+        assert_eq!(counter.read(&mut buffer).unwrap(), 5);
+        assert_eq!(&buffer, b"abcde");
+        assert_eq!(counter.position(), 5);
+        assert_eq!(counter.count(), 5);
+
+        assert_eq!(counter.peek(10), b"fgh");
+        assert_eq!(counter.read(&mut buffer).unwrap(), 3);
+        assert_eq!(counter.peek(1), b"");
+        assert_eq!(counter.count(), 8);
+    }
+
+    #[test]
+    fn seek() {
+        let mut counter = SliceReadCounter::from(&b"abcdefgh"[..]);
+
+        assert_eq!(counter.seek(SeekFrom::End(-2)).unwrap(), 6);
+        assert_eq!(counter.peek(2), b"gh");
+        assert_eq!(counter.count(), 0);
+        assert!(counter.seek(SeekFrom::Current(3)).is_err());
+        assert_eq!(counter.position(), 6);
+    }
+}
@@ -0,0 +1,86 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::collections::HashMap;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// A point-in-time snapshot of a counter, created by `snapshot()`.
+///
+/// Snapshots are read-only: taking one does not affect the counter. Two snapshots of the same
+/// counter can be compared with `delta()` to measure the activity in the interval between them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CounterSnapshot {
+    /// The number of bytes counted when the snapshot was taken.
+    pub bytes: usize,
+
+    /// The number of operations counted when the snapshot was taken.
+    pub ops: usize,
+
+    /// The time when the snapshot was taken.
+    pub at: Instant,
+}
+
+/// The difference between two `CounterSnapshot`s, created by `CounterSnapshot::delta()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CounterDelta {
+    /// The number of bytes counted in the interval.
+    pub bytes: usize,
+
+    /// The number of operations counted in the interval.
+    pub ops: usize,
+
+    /// The length of the interval.
+    pub elapsed: Duration,
+}
+
+/// The final metrics of a counter, created by `finalize()`.
+///
+/// Unlike the counter itself, a report does not borrow the wrapped stream, so it can be kept or
+/// passed around freely once processing is finished. Metrics which the counter does not track are
+/// `None` (or empty).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CountReport {
+    /// The number of bytes counted.
+    pub bytes: usize,
+
+    /// The number of operations counted.
+    pub ops: usize,
+
+    /// The number of newline bytes read or written, if counted.
+    pub lines: Option<usize>,
+
+    /// The number of bytes written but not yet successfully flushed, for writers.
+    pub pending: Option<usize>,
+
+    /// The number of errors returned by the wrapped stream, broken down by kind, for readers.
+    pub error_kinds: HashMap<io::ErrorKind, usize>,
+}
+
+impl CounterSnapshot {
+    /// Returns the activity between an `earlier` snapshot and this one.
+    ///
+    /// If `earlier` was actually taken after this snapshot, the fields saturate at zero.
+    pub fn delta(&self, earlier: &CounterSnapshot) -> CounterDelta {
+        CounterDelta {
+            bytes: self.bytes.saturating_sub(earlier.bytes),
+            ops: self.ops.saturating_sub(earlier.ops),
+            elapsed: self.at.saturating_duration_since(earlier.at),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and allowing a prefix of
+/// the stream to be peeked.
+///
+/// A `SniffReadCounter` behaves just like a `ReadCounter`. However, `peek_prefix()` can inspect
+/// the next bytes of the stream (such as a magic number) before deciding how to process it.
+/// Peeked bytes are buffered and served by later calls to `read`, so they are only counted once
+/// they are actually read.
+pub struct SniffReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    buffer: Vec<u8>,
+    pos: usize,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for SniffReadCounter<'a, T> {
+    /// Creates a `SniffReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> SniffReadCounter<'a, T> {
+        SniffReadCounter {
+            reader: value,
+            count: 0,
+            buffer: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> SniffReadCounter<'a, T> {
+    /// Returns the next `n` bytes of the stream without consuming them.
+    ///
+    /// This calls the inner `read` function as many times as needed. If the stream ends first,
+    /// the returned prefix is shorter than `n`. The bytes are not counted until they are read.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors (other than with the kind
+    /// `Interrupted`, which is retried). Any bytes buffered before the error are kept.
+    pub fn peek_prefix(&mut self, n: usize) -> io::Result<&[u8]> {
+        let mut scratch = [0u8; 512];
+
+        while self.buffer.len() - self.pos < n {
+            let want = cmp::min(scratch.len(), n - (self.buffer.len() - self.pos));
+            match self.reader.read(&mut scratch[..want]) {
+                Ok(0) => break,
+                Ok(size) => self.buffer.extend_from_slice(&scratch[..size]),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let end = cmp::min(self.buffer.len(), self.pos + n);
+        Ok(&self.buffer[self.pos..end])
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for SniffReadCounter<'a, T> {
+    /// Returns the number of bytes read so far, excluding bytes which were only peeked.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for SniffReadCounter<'a, T> {
+    /// Serves any peeked bytes, then proxies to the inner `read` function, counting the bytes read
+    /// along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        if self.pos < self.buffer.len() {
+            let size = cmp::min(buffer.len(), self.buffer.len() - self.pos);
+            buffer[..size].copy_from_slice(&self.buffer[self.pos..self.pos + size]);
+            self.pos += size;
+
+            if self.pos == self.buffer.len() {
+                self.buffer.clear();
+                self.pos = 0;
+            }
+
+            self.count += size;
+            return Ok(size);
+        }
+
+        let size = self.reader.read(buffer)?;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use testutil::Chunked;
+
+    #[test]
+    fn sniff() {
+        let data = b"\x89PNG\r\n\x1a\nrest";
+        let mut c = Chunked(data, 3);
+        let mut r = SniffReadCounter::from(&mut c);
+
+        assert_eq!(r.peek_prefix(4).unwrap(), b"\x89PNG");
+        assert_eq!(r.peek_prefix(2).unwrap(), b"\x89P");
+        assert_eq!(r.count(), 0);
+
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(&out[..], &data[..]);
+        assert_eq!(r.count(), data.len());
+    }
+
+    #[test]
+    fn short() {
+        let mut d = &b"ab"[..];
+        let mut r = SniffReadCounter::from(&mut d);
+
+        assert_eq!(r.peek_prefix(4).unwrap(), b"ab");
+        assert_eq!(r.count(), 0);
+    }
+}
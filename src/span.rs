@@ -0,0 +1,168 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use Counter;
+
+/// A burst of reads, recorded by a `SpanReadCounter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    /// The offset of the first byte read in the burst.
+    pub start_offset: usize,
+
+    /// The offset just past the last byte read in the burst.
+    pub end_offset: usize,
+
+    /// The time between the first and the last read of the burst.
+    pub duration: Duration,
+}
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and grouping reads into
+/// bursts.
+///
+/// A `SpanReadCounter` behaves just like a `ReadCounter`. However, it also records each burst of
+/// reads as a `Span`. A new burst begins whenever more than the configured gap has elapsed since
+/// the previous non-empty read completed. Empty reads are ignored. The spans are suitable for
+/// exporting to a tracing system, though this crate does not depend on one.
+pub struct SpanReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    gap: Duration,
+    spans: Vec<Span>,
+    current: Option<(Instant, Instant)>,
+}
+
+impl<'a, T: 'a + io::Read> SpanReadCounter<'a, T> {
+    /// Creates a `SpanReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// Reads separated by more than `gap` are recorded in separate spans.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, gap: Duration) -> SpanReadCounter<'a, T> {
+        SpanReadCounter {
+            reader,
+            count: 0,
+            gap,
+            spans: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// Returns the spans recorded so far, including the one in progress.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    fn update(&mut self, now: Instant, size: usize) {
+        let start = self.count;
+        self.count += size;
+
+        match self.current {
+            Some((first, last)) if now.duration_since(last) <= self.gap => {
+                let span = self.spans.last_mut().unwrap();
+                span.end_offset = self.count;
+                span.duration = now.duration_since(first);
+                self.current = Some((first, now));
+            }
+
+            _ => {
+                self.spans.push(Span {
+                    start_offset: start,
+                    end_offset: self.count,
+                    duration: Duration::from_secs(0),
+                });
+                self.current = Some((now, now));
+            }
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for SpanReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for SpanReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+
+        if size > 0 {
+            self.update(Instant::now(), size);
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn reads() {
+        let data = [0u8; 6];
+        let mut d = &data[..];
+        let mut r = SpanReadCounter::new(&mut d, Duration::from_secs(3600));
+        let mut buf = [0u8; 4];
+
+        assert_eq!(r.read(&mut buf).unwrap(), 4);
+        assert_eq!(r.read(&mut buf).unwrap(), 2);
+        assert_eq!(r.read(&mut buf).unwrap(), 0);
+
+        let spans = r.spans();
+        assert_eq!(spans.len(), 1);
+        assert_eq!((spans[0].start_offset, spans[0].end_offset), (0, 6));
+        assert_eq!(r.count(), 6);
+    }
+
+    #[test]
+    fn bursts() {
+        let mut d = &b""[..];
+        let mut r = SpanReadCounter::new(&mut d, Duration::from_millis(100));
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        r.update(at(0), 2);
+        r.update(at(100), 2);
+        r.update(at(201), 2);
+
+        let spans = r.spans();
+        assert_eq!(spans.len(), 2);
+        assert_eq!((spans[0].start_offset, spans[0].end_offset), (0, 4));
+        assert_eq!(spans[0].duration, Duration::from_millis(100));
+        assert_eq!((spans[1].start_offset, spans[1].end_offset), (4, 6));
+        assert_eq!(spans[1].duration, Duration::from_secs(0));
+        assert_eq!(r.count(), 6);
+    }
+}
@@ -0,0 +1,186 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+
+use Counter;
+
+/// Summary statistics of the byte values seen by a `StatsReadCounter`.
+///
+/// Each field is `None` if no bytes have been read.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ByteStats {
+    /// The smallest byte value read.
+    pub min: Option<u8>,
+
+    /// The largest byte value read.
+    pub max: Option<u8>,
+
+    /// The mean of the byte values read.
+    pub mean: Option<f64>,
+}
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and computing summary
+/// statistics of their values.
+///
+/// A `StatsReadCounter` behaves just like a `ReadCounter`. However, it also tracks the minimum,
+/// maximum and mean byte values read. This is a cheap way to characterize a stream's data. The
+/// sizes of the non-empty reads are tracked as well, using Welford's online algorithm for their
+/// variance.
+pub struct StatsReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    min: u8,
+    max: u8,
+    sum: u64,
+    reads: usize,
+    mean: f64,
+    m2: f64,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for StatsReadCounter<'a, T> {
+    /// Creates a `StatsReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> StatsReadCounter<'a, T> {
+        StatsReadCounter {
+            reader: value,
+            count: 0,
+            min: u8::MAX,
+            max: u8::MIN,
+            sum: 0,
+            reads: 0,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> StatsReadCounter<'a, T> {
+    /// Returns summary statistics of the byte values read so far.
+    pub fn byte_stats(&self) -> ByteStats {
+        if self.count == 0 {
+            return ByteStats {
+                min: None,
+                max: None,
+                mean: None,
+            };
+        }
+
+        ByteStats {
+            min: Some(self.min),
+            max: Some(self.max),
+            mean: Some(self.sum as f64 / self.count as f64),
+        }
+    }
+
+    /// Returns the mean size of the non-empty reads so far.
+    ///
+    /// This is zero if no bytes have been read.
+    pub fn read_size_mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Returns the standard deviation of the sizes of the non-empty reads so far.
+    ///
+    /// This is zero until at least two non-empty reads have completed. A high value relative to
+    /// the mean indicates bursty reads.
+    pub fn read_size_stddev(&self) -> f64 {
+        if self.reads < 2 {
+            return 0.0;
+        }
+
+        (self.m2 / self.reads as f64).sqrt()
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for StatsReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for StatsReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+
+        for byte in &buffer[..size] {
+            self.min = self.min.min(*byte);
+            self.max = self.max.max(*byte);
+            self.sum += u64::from(*byte);
+        }
+
+        if size > 0 {
+            self.reads += 1;
+            let delta = size as f64 - self.mean;
+            self.mean += delta / self.reads as f64;
+            self.m2 += delta * (size as f64 - self.mean);
+        }
+
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn stats() {
+        let data = [10u8, 200, 30, 40];
+        let mut d = &data[..];
+        let mut r = StatsReadCounter::from(&mut d);
+        assert_eq!(r.byte_stats().mean, None);
+
+        let mut buf = [0u8; 3];
+        while r.read(&mut buf).unwrap() > 0 {}
+
+        let stats = r.byte_stats();
+        assert_eq!(stats.min, Some(10));
+        assert_eq!(stats.max, Some(200));
+        assert_eq!(stats.mean, Some(70.0));
+        assert_eq!(r.count(), 4);
+    }
+
+    #[test]
+    fn read_sizes() {
+        let data = [0u8; 40];
+        let mut d = &data[..];
+        let mut r = StatsReadCounter::from(&mut d);
+
+        for size in &[2, 4, 4, 4, 5, 5, 7, 9] {
+            r.read_exact(&mut vec![0u8; *size][..]).unwrap();
+        }
+        assert_eq!(r.read(&mut [0u8; 4]).unwrap(), 0);
+        assert!((r.read_size_mean() - 5.0).abs() < 1e-9);
+        assert!((r.read_size_stddev() - 2.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,116 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read per caller-defined tag.
+///
+/// A `TaggedReadCounter` passes data through just like a `ReadCounter`. However, after each
+/// non-empty read it calls the classifier with the bytes just read and attributes all of them to
+/// the returned tag. This attributes the bytes of one stream to logical categories in a single
+/// pass. The classifier is not called for empty reads.
+pub struct TaggedReadCounter<'a, T: 'a + io::Read, K: Eq + Hash, F: FnMut(&[u8]) -> K> {
+    reader: &'a mut T,
+    count: usize,
+    classifier: F,
+    counts: HashMap<K, usize>,
+}
+
+impl<'a, T: 'a + io::Read, K: Eq + Hash, F: FnMut(&[u8]) -> K> TaggedReadCounter<'a, T, K, F> {
+    /// Creates a `TaggedReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, classifier: F) -> TaggedReadCounter<'a, T, K, F> {
+        TaggedReadCounter {
+            reader,
+            count: 0,
+            classifier,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of bytes attributed to `tag` so far.
+    pub fn count_for(&self, tag: K) -> usize {
+        self.counts.get(&tag).cloned().unwrap_or(0)
+    }
+
+    /// Returns the number of bytes attributed to each tag seen so far.
+    pub fn counts(&self) -> &HashMap<K, usize> {
+        &self.counts
+    }
+}
+
+impl<'a, T: 'a + io::Read, K: Eq + Hash, F: FnMut(&[u8]) -> K> Counter
+    for TaggedReadCounter<'a, T, K, F>
+{
+    /// Returns the total number of bytes read so far, across all tags.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read, K: Eq + Hash, F: FnMut(&[u8]) -> K> io::Read
+    for TaggedReadCounter<'a, T, K, F>
+{
+    /// Proxies to the inner `read` function, counting the bytes read under their tag.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function or the classifier panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+
+        if size > 0 {
+            let tag = (self.classifier)(&buffer[..size]);
+            *self.counts.entry(tag).or_insert(0) += size;
+        }
+
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn tags() {
+        let mut d = &b"abcd12ef34"[..];
+        let mut r = TaggedReadCounter::new(&mut d, |bytes: &[u8]| bytes[0].is_ascii_digit());
+
+        let mut buffer = [0u8; 2];
+        while r.read(&mut buffer).unwrap() > 0 {}
+
+        assert_eq!(r.count_for(false), 6);
+        assert_eq!(r.count_for(true), 4);
+        assert_eq!(r.counts().len(), 2);
+        assert_eq!(r.count(), 10);
+    }
+}
@@ -0,0 +1,121 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use std::time::Instant;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and recording when the
+/// first and last bytes arrived.
+///
+/// A `TimestampReadCounter` behaves just like a `ReadCounter`. However, it also records the time
+/// of the first and the most recent non-empty reads. Unlike timing from the creation of the
+/// counter, this excludes any setup latency before data starts flowing.
+pub struct TimestampReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    first: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for TimestampReadCounter<'a, T> {
+    /// Creates a `TimestampReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> TimestampReadCounter<'a, T> {
+        TimestampReadCounter {
+            reader: value,
+            count: 0,
+            first: None,
+            last: None,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> TimestampReadCounter<'a, T> {
+    /// Returns when the first non-empty read completed, if any.
+    pub fn first_byte_at(&self) -> Option<Instant> {
+        self.first
+    }
+
+    /// Returns when the most recent non-empty read completed, if any.
+    pub fn last_byte_at(&self) -> Option<Instant> {
+        self.last
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for TimestampReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for TimestampReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+
+        if size > 0 {
+            let now = Instant::now();
+            self.first = self.first.or(Some(now));
+            self.last = Some(now);
+        }
+
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn timestamps() {
+        let data = [1u8, 2, 3];
+        let mut d = &data[..];
+        let mut r = TimestampReadCounter::from(&mut d);
+        let mut buf = [0u8; 2];
+
+        assert_eq!(r.first_byte_at(), None);
+        assert_eq!(r.read(&mut buf).unwrap(), 2);
+        assert_eq!(r.first_byte_at(), r.last_byte_at());
+
+        sleep(Duration::from_millis(20));
+        assert_eq!(r.read(&mut buf).unwrap(), 1);
+        assert_eq!(r.read(&mut buf).unwrap(), 0);
+
+        let (first, last) = (r.first_byte_at().unwrap(), r.last_byte_at().unwrap());
+        assert!(last.duration_since(first) >= Duration::from_millis(20));
+        assert_eq!(r.count(), 3);
+    }
+}
@@ -0,0 +1,154 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and limiting the rate
+/// with a token bucket.
+///
+/// A `TokenBucketReadCounter` holds up to `capacity` tokens, and each byte read consumes one. The
+/// bucket refills continuously at `rate` tokens per second. Reads are truncated to the tokens
+/// available, and only block when the bucket is empty. So bursts of up to `capacity` bytes pass
+/// immediately, while the sustained rate is capped at `rate` bytes per second. The bucket starts
+/// full. Once it is empty, `read` waits for a batch of tokens (about 10ms worth, up to the size of
+/// the buffer and the capacity), rather than reading a byte at a time.
+pub struct TokenBucketReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    refilled: Instant,
+    waits: usize,
+}
+
+impl<'a, T: 'a + io::Read> TokenBucketReadCounter<'a, T> {
+    /// Creates a `TokenBucketReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is zero or `rate` is not positive.
+    #[must_use]
+    pub fn new(reader: &'a mut T, capacity: usize, rate: f64) -> TokenBucketReadCounter<'a, T> {
+        assert!(capacity > 0, "capacity must be nonzero");
+        assert!(rate > 0.0, "rate must be positive");
+
+        TokenBucketReadCounter {
+            reader,
+            count: 0,
+            capacity: capacity as f64,
+            rate,
+            tokens: capacity as f64,
+            refilled: Instant::now(),
+            waits: 0,
+        }
+    }
+
+    /// Returns the number of whole tokens currently in the bucket.
+    pub fn tokens(&self) -> usize {
+        self.available(Instant::now()) as usize
+    }
+
+    // Returns the tokens in the bucket at `now`, including those refilled since the last read.
+    fn available(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        (self.tokens + elapsed * self.rate).min(self.capacity)
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        self.tokens = self.available(now);
+        self.refilled = now;
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for TokenBucketReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for TokenBucketReadCounter<'a, T> {
+    /// Waits for tokens if the bucket is empty, then proxies to the inner `read` function,
+    /// counting the bytes read along the way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors. No tokens are consumed
+    /// in that case.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        if buffer.is_empty() {
+            return self.reader.read(buffer);
+        }
+
+        self.refill();
+        if self.tokens < 1.0 {
+            let batch = (self.rate / 100.0)
+                .min(buffer.len() as f64)
+                .min(self.capacity)
+                .max(1.0);
+
+            self.waits += 1;
+            thread::sleep(Duration::from_secs_f64((batch - self.tokens) / self.rate));
+            self.refill();
+        }
+
+        let max = buffer.len().min((self.tokens as usize).max(1));
+        let size = self.reader.read(&mut buffer[..max])?;
+        self.tokens -= size as f64;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn burst() {
+        let data = [0u8; 200];
+        let mut d = &data[..];
+        let mut r = TokenBucketReadCounter::new(&mut d, 100, 2000.0);
+        let mut buffer = [0u8; 100];
+
+        let start = Instant::now();
+        r.read_exact(&mut buffer).unwrap();
+        assert_eq!(r.waits, 0);
+
+        // The remaining 100 bytes must wait for the bucket to refill at 2000 bytes per second. It
+        // does so in batches of 20 tokens (10ms at that rate), so it waits at most five times.
+        r.read_exact(&mut buffer).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(45));
+        assert!(r.waits >= 1 && r.waits <= 5);
+        assert_eq!(r.count(), 200);
+    }
+}
@@ -0,0 +1,211 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use {Counter, TransformCounter};
+
+/// Wraps any implementation of `std::io::Read`, transforming the bytes read and counting both the
+/// input and the output.
+///
+/// A `TransformReadCounter` reads chunks from the wrapped reader and passes each one to a
+/// user-supplied function, which appends the transformed bytes (for example, decrypted data) to
+/// an output buffer. The transformed bytes are then delivered to the caller. Any state needed to
+/// handle partial input (such as an incomplete block) is the responsibility of the function.
+pub struct TransformReadCounter<'a, T: 'a + io::Read, F: FnMut(&[u8], &mut Vec<u8>)> {
+    reader: &'a mut T,
+    transform: F,
+    input: usize,
+    output: usize,
+    buffer: Vec<u8>,
+    pos: usize,
+}
+
+impl<'a, T: 'a + io::Read, F: FnMut(&[u8], &mut Vec<u8>)> TransformReadCounter<'a, T, F> {
+    /// Creates a `TransformReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    #[must_use]
+    pub fn new(reader: &'a mut T, transform: F) -> TransformReadCounter<'a, T, F> {
+        TransformReadCounter {
+            reader,
+            transform,
+            input: 0,
+            output: 0,
+            buffer: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Returns the number of bytes read from the wrapped reader so far.
+    pub fn input_count(&self) -> usize {
+        self.input
+    }
+
+    /// Returns the number of transformed bytes delivered to the caller so far.
+    pub fn output_count(&self) -> usize {
+        self.output
+    }
+}
+
+impl<'a, T: 'a + io::Read, F: FnMut(&[u8], &mut Vec<u8>)> Counter
+    for TransformReadCounter<'a, T, F>
+{
+    /// Returns the number of transformed bytes delivered to the caller so far.
+    fn count(&self) -> usize {
+        self.output
+    }
+}
+
+impl<'a, T: 'a + io::Read, F: FnMut(&[u8], &mut Vec<u8>)> TransformCounter
+    for TransformReadCounter<'a, T, F>
+{
+    /// Returns the number of bytes read from the wrapped reader so far.
+    fn input_count(&self) -> usize {
+        self.input
+    }
+
+    /// Returns the number of transformed bytes delivered to the caller so far.
+    fn output_count(&self) -> usize {
+        self.output
+    }
+}
+
+impl<'a, T: 'a + io::Read, F: FnMut(&[u8], &mut Vec<u8>)> io::Read
+    for TransformReadCounter<'a, T, F>
+{
+    /// Reads from the inner reader, transforming and counting the bytes along the way.
+    ///
+    /// If the function produces no output for a chunk, this function reads again until some
+    /// output is produced or the end of the stream is reached.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function or the transform panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        let mut scratch = [0u8; 4096];
+
+        while self.pos == self.buffer.len() {
+            self.buffer.clear();
+            self.pos = 0;
+
+            let max = cmp::min(buffer.len(), scratch.len());
+            let size = self.reader.read(&mut scratch[..max])?;
+            self.input += size;
+
+            if size == 0 {
+                return Ok(0);
+            }
+
+            (self.transform)(&scratch[..size], &mut self.buffer);
+        }
+
+        let size = cmp::min(buffer.len(), self.buffer.len() - self.pos);
+        buffer[..size].copy_from_slice(&self.buffer[self.pos..self.pos + size]);
+        self.pos += size;
+        self.output += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn identity() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut d = &data[..];
+        let mut r =
+            TransformReadCounter::new(&mut d, |i: &[u8], o: &mut Vec<u8>| o.extend_from_slice(i));
+
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data);
+        assert_eq!(r.input_count(), 100);
+        assert_eq!(r.output_count(), 100);
+    }
+
+    #[test]
+    fn expanding() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut d = &data[..];
+        let mut r = TransformReadCounter::new(&mut d, |i: &[u8], o: &mut Vec<u8>| {
+            for byte in i {
+                o.push(*byte);
+                o.push(*byte);
+            }
+        });
+
+        let mut out = Vec::new();
+        let mut buf = [0u8; 7];
+        loop {
+            match r.read(&mut buf).unwrap() {
+                0 => break,
+                n => out.extend_from_slice(&buf[..n]),
+            }
+        }
+
+        assert_eq!(out.len(), 200);
+        assert_eq!(&out[..4], &[0, 0, 1, 1]);
+        assert_eq!(r.input_count(), 100);
+        assert_eq!(r.output_count(), 200);
+        assert_eq!(r.count(), 200);
+    }
+
+    #[test]
+    fn transform_counter() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut d = &data[..];
+        let mut r = TransformReadCounter::new(&mut d, |i: &[u8], o: &mut Vec<u8>| {
+            o.extend(i.iter().filter(|b| *b % 2 == 0))
+        });
+
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).unwrap();
+
+        let t: &dyn TransformCounter = &r;
+        assert_eq!((t.input_count(), t.output_count()), (10, 5));
+    }
+
+    #[test]
+    fn ratio() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut d = &data[..];
+        let mut r = TransformReadCounter::new(&mut d, |i: &[u8], o: &mut Vec<u8>| {
+            o.extend_from_slice(i);
+            o.extend_from_slice(i);
+        });
+        assert_eq!(r.ratio(), 0.0);
+
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(r.ratio(), 2.0);
+    }
+}
@@ -0,0 +1,215 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use std::str;
+
+use Counter;
+
+fn chars(valid: &[u8]) -> usize {
+    valid.iter().filter(|b| (**b & 0xC0) != 0x80).count()
+}
+
+fn invalid() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and validating them as
+/// UTF-8.
+///
+/// A `Utf8ReadCounter` behaves just like a `ReadCounter`. However, it also validates that the
+/// bytes read form valid UTF-8 and counts the number of characters read. Validation is incremental:
+/// a multi-byte sequence which is split across calls to `read` is buffered internally until it is
+/// complete. An incomplete sequence at the end of the stream is not reported as an error by `read`;
+/// call `finish()` after reaching the end of the stream to detect it.
+pub struct Utf8ReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    chars: usize,
+    pending: [u8; 4],
+    pending_len: usize,
+}
+
+impl<'a, T: 'a + io::Read> From<&'a mut T> for Utf8ReadCounter<'a, T> {
+    /// Creates a `Utf8ReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    fn from(value: &'a mut T) -> Utf8ReadCounter<'a, T> {
+        Utf8ReadCounter {
+            reader: value,
+            count: 0,
+            chars: 0,
+            pending: [0u8; 4],
+            pending_len: 0,
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> Utf8ReadCounter<'a, T> {
+    /// Returns the number of complete characters read so far.
+    pub fn chars(&self) -> usize {
+        self.chars
+    }
+
+    /// Consumes this instance, returning the number of characters read.
+    ///
+    /// This is intended to be called once the end of the stream has been reached.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error of kind `InvalidData` if the stream ended with an
+    /// incomplete multi-byte sequence, such as in a truncated file.
+    pub fn finish(self) -> Result<usize, io::Error> {
+        if self.pending_len > 0 {
+            return Err(invalid());
+        }
+
+        Ok(self.chars)
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for Utf8ReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for Utf8ReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting and validating the bytes read.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the underlying function errors. It will also return an error
+    /// of kind `InvalidData` if the bytes read are not valid UTF-8. In this case, the bytes from
+    /// the failed call are not counted.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+        let data = &buffer[..size];
+
+        let mut pending = self.pending;
+        let mut pending_len = self.pending_len;
+        let mut chars = 0;
+        let mut start = 0;
+
+        // Complete any sequence left over from the previous read, one byte at a time.
+        while pending_len > 0 && start < data.len() {
+            pending[pending_len] = data[start];
+            pending_len += 1;
+            start += 1;
+
+            match str::from_utf8(&pending[..pending_len]) {
+                Ok(_) => {
+                    chars += 1;
+                    pending_len = 0;
+                }
+                Err(ref e) if e.error_len().is_some() => return Err(invalid()),
+                Err(_) => {}
+            }
+        }
+
+        if pending_len == 0 {
+            let rest = &data[start..];
+            match str::from_utf8(rest) {
+                Ok(_) => chars += self::chars(rest),
+                Err(ref e) if e.error_len().is_some() => return Err(invalid()),
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    chars += self::chars(&rest[..valid]);
+                    pending_len = rest.len() - valid;
+                    pending[..pending_len].copy_from_slice(&rest[valid..]);
+                }
+            }
+        }
+
+        self.pending = pending;
+        self.pending_len = pending_len;
+        self.chars += chars;
+        self.count += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn split() {
+        let data = "h\u{e9}llo \u{20ac}!".as_bytes();
+        let mut d = data;
+        let mut r = Utf8ReadCounter::from(&mut d);
+        let mut buf = [0u8; 16];
+
+        // Split in the middle of the two-byte `é`.
+        assert_eq!(r.read(&mut buf[..2]).unwrap(), 2);
+        assert_eq!(r.chars(), 1);
+
+        // Split twice within the three-byte `€`.
+        assert_eq!(r.read(&mut buf[..6]).unwrap(), 6);
+        assert_eq!(r.chars(), 6);
+        assert_eq!(r.read(&mut buf[..1]).unwrap(), 1);
+        assert_eq!(r.chars(), 6);
+        assert_eq!(r.read(&mut buf).unwrap(), 2);
+        assert_eq!(r.chars(), 8);
+
+        assert_eq!(r.read(&mut buf).unwrap(), 0);
+        assert_eq!(r.count(), data.len());
+    }
+
+    #[test]
+    fn invalid() {
+        let data = [b'a', 0xC3, 0xFF];
+        let mut d = &data[..];
+        let mut r = Utf8ReadCounter::from(&mut d);
+        let mut buf = [0u8; 2];
+
+        assert_eq!(r.read(&mut buf).unwrap(), 2);
+        assert_eq!(r.chars(), 1);
+
+        let err = r.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(r.count(), 2);
+    }
+
+    #[test]
+    fn finish() {
+        let data = "a\u{20ac}".as_bytes();
+        let mut d = data;
+        let mut r = Utf8ReadCounter::from(&mut d);
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(r.finish().unwrap(), 2);
+
+        let mut d = &data[..3];
+        let mut r = Utf8ReadCounter::from(&mut d);
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(r.count(), 3);
+        assert_eq!(r.finish().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
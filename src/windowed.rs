@@ -0,0 +1,158 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use crc32;
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and computing a CRC-32
+/// checksum of each fixed-size window of the stream.
+///
+/// A `WindowedChecksumReadCounter` behaves just like a `ReadCounter`. However, every `window`
+/// bytes it finalizes the CRC-32 of that window and stores it. Comparing these against known
+/// good values localizes corruption to a region, rather than failing the whole stream. Window
+/// boundaries are based on the running count, so they do not depend on how reads are split.
+pub struct WindowedChecksumReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    window: usize,
+    crc: u32,
+    checksums: Vec<u32>,
+}
+
+impl<'a, T: 'a + io::Read> WindowedChecksumReadCounter<'a, T> {
+    /// Creates a `WindowedChecksumReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    ///
+    /// # Panics
+    ///
+    /// If `window` is zero.
+    #[must_use]
+    pub fn new(reader: &'a mut T, window: usize) -> WindowedChecksumReadCounter<'a, T> {
+        assert!(window > 0, "window must be nonzero");
+
+        WindowedChecksumReadCounter {
+            reader,
+            count: 0,
+            window,
+            crc: 0,
+            checksums: Vec::new(),
+        }
+    }
+
+    /// Returns the CRC-32 checksums of each complete window read so far.
+    pub fn window_checksums(&self) -> &[u32] {
+        &self.checksums
+    }
+
+    /// Returns the CRC-32 checksum of the incomplete window currently being read, if any.
+    ///
+    /// At the end of the stream, this is the checksum of the final, short window.
+    pub fn partial_checksum(&self) -> Option<u32> {
+        match self.count % self.window {
+            0 => None,
+            _ => Some(self.crc),
+        }
+    }
+
+    fn update(&mut self, mut buffer: &[u8]) {
+        while !buffer.is_empty() {
+            let room = self.window - self.count % self.window;
+            let size = cmp::min(room, buffer.len());
+
+            self.crc = crc32::update(self.crc, &buffer[..size]);
+            self.count += size;
+            buffer = &buffer[size..];
+
+            if size == room {
+                self.checksums.push(self.crc);
+                self.crc = 0;
+            }
+        }
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for WindowedChecksumReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for WindowedChecksumReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting and checksumming the bytes read along the
+    /// way.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let size = self.reader.read(buffer)?;
+        self.update(&buffer[..size]);
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn windows() {
+        let data: Vec<u8> = (0..250u32).map(|i| (i * 31) as u8).collect();
+        let mut d = &data[..];
+        let mut r = WindowedChecksumReadCounter::new(&mut d, 64);
+        let mut buf = [0u8; 37];
+
+        while r.read(&mut buf).unwrap() > 0 {}
+
+        assert_eq!(
+            r.window_checksums(),
+            &[0x44D2_4382, 0x68CA_F1CA, 0xD1D0_52A4]
+        );
+        assert_eq!(r.partial_checksum(), Some(0x0496_0489));
+        assert_eq!(r.count(), 250);
+    }
+
+    #[test]
+    fn check_value() {
+        let data = b"123456789123456789abcdefghi";
+        let mut d = &data[..];
+        let mut r = WindowedChecksumReadCounter::new(&mut d, 9);
+        let mut buf = [0u8; 4];
+
+        while r.read(&mut buf).unwrap() > 0 {}
+
+        // 0xCBF43926 is the standard CRC-32 check value, for "123456789".
+        assert_eq!(
+            r.window_checksums(),
+            &[0xCBF4_3926, 0xCBF4_3926, 0x8DA9_88AF]
+        );
+        assert_eq!(r.partial_checksum(), None);
+        assert_eq!(r.count(), 27);
+    }
+}
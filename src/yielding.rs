@@ -0,0 +1,131 @@
+//
+// Copyright 2018 Red Hat, Inc.
+//
+// Author: Nathaniel McCallum <npmccallum@redhat.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::cmp;
+use std::io;
+
+use Counter;
+
+/// Wraps any implementation of `std::io::Read`, counting the bytes read and periodically returning
+/// short reads for flow control.
+///
+/// A `YieldReadCounter` tracks the bytes read since it last yielded. Reads are truncated so they
+/// never cross the `watermark`, and a read which reaches it yields control back to the caller,
+/// starting a new window. Unlike a `ChunkedReadCounter`, which limits the size of each call, this
+/// limits the cumulative bytes between yields, so a loop of small reads still yields every
+/// `watermark` bytes.
+pub struct YieldReadCounter<'a, T: 'a + io::Read> {
+    reader: &'a mut T,
+    count: usize,
+    watermark: usize,
+    since_yield: usize,
+    yields: usize,
+}
+
+impl<'a, T: 'a + io::Read> YieldReadCounter<'a, T> {
+    /// Creates a `YieldReadCounter` by wrapping any implementation of `std::io::Read`.
+    ///
+    /// The lifetime of this instance cannot be greater than the lifetime of the wrapped instance.
+    ///
+    /// # Panics
+    ///
+    /// If `watermark` is zero.
+    #[must_use]
+    pub fn new(reader: &'a mut T, watermark: usize) -> YieldReadCounter<'a, T> {
+        assert!(watermark > 0, "watermark must be nonzero");
+
+        YieldReadCounter {
+            reader,
+            count: 0,
+            watermark,
+            since_yield: 0,
+            yields: 0,
+        }
+    }
+
+    /// Returns the number of bytes which can be read before the next yield.
+    pub fn until_yield(&self) -> usize {
+        self.watermark - self.since_yield
+    }
+
+    /// Returns the number of times a read has yielded at the watermark.
+    pub fn yields(&self) -> usize {
+        self.yields
+    }
+}
+
+impl<'a, T: 'a + io::Read> Counter for YieldReadCounter<'a, T> {
+    /// Returns the number of bytes read so far.
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a, T: 'a + io::Read> io::Read for YieldReadCounter<'a, T> {
+    /// Proxies to the inner `read` function, counting the bytes read along the way.
+    ///
+    /// The buffer passed to the inner reader is truncated so the read does not cross the
+    /// watermark.
+    ///
+    /// # Panics
+    ///
+    ///   1. When the underlying function panics.
+    ///   2. If more than `usize::max_value()` bytes are read across all calls to `read`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error only if the underlying function errors.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, io::Error> {
+        let limit = cmp::min(buffer.len(), self.until_yield());
+        let size = self.reader.read(&mut buffer[..limit])?;
+
+        self.count += size;
+        self.since_yield += size;
+        if self.since_yield == self.watermark {
+            self.since_yield = 0;
+            self.yields += 1;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn watermark() {
+        let data = [0u8; 12];
+        let mut d = &data[..];
+        let mut r = YieldReadCounter::new(&mut d, 5);
+        let mut small = [0u8; 3];
+        let mut big = [0u8; 100];
+
+        assert_eq!(r.read(&mut small).unwrap(), 3);
+        assert_eq!(r.read(&mut big).unwrap(), 2);
+        assert_eq!(r.yields(), 1);
+        assert_eq!(r.read(&mut big).unwrap(), 5);
+        assert_eq!(r.yields(), 2);
+        assert_eq!(r.read(&mut big).unwrap(), 2);
+        assert_eq!(r.until_yield(), 3);
+        assert_eq!(r.read(&mut big).unwrap(), 0);
+        assert_eq!(r.count(), 12);
+    }
+}